@@ -0,0 +1,219 @@
+//! Times a full Dijkstra flood and an A* search between the generated end
+//! points over large grids with every open list, checking they all reach the
+//! same cells at the same cost and find equally short paths.
+//!
+//! ```text
+//! open_lists [--size <n>] [--runs <n>] [--seed <n>]
+//! ```
+//!
+//! For a flood `cells` is the number of cells reached and `cost` their total
+//! cost; for A* they are the cells closed and the cost of the path found.
+use a_star_graph::{
+    AStar, AStarTrait, BucketQueue, CornerCutting, Direction, Generator, Grid, GridType,
+    MovementRange, OpenList, Position, RadixHeap,
+};
+use priority_queue::PriorityQueue;
+use std::cmp::Reverse;
+use std::convert::TryFrom;
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
+
+const OPEN_LISTS: [&str; 3] = ["PriorityQueue", "BucketQueue", "RadixHeap"];
+
+/// Floods the grid `runs` times, at least once, and returns the last range
+/// with the average time taken.
+fn flood<Q: OpenList<Position, Key = usize>>(
+    grid: &Grid,
+    origin: Position,
+    diagonal: bool,
+    runs: u32,
+    open: impl Fn() -> Q,
+) -> (MovementRange, Duration) {
+    let grid_type = GridType::Full(grid);
+    let run = || {
+        let then = Instant::now();
+        let range = MovementRange::with_open_list(
+            grid_type,
+            origin,
+            usize::MAX,
+            diagonal,
+            CornerCutting::Always,
+            open(),
+        );
+        (range, then.elapsed())
+    };
+    let (mut range, mut time) = run();
+    for _ in 1..runs {
+        let (last, elapsed) = run();
+        range = last;
+        time += elapsed;
+    }
+    (range, time / runs.max(1))
+}
+
+/// Solves from `start` to `target` `runs` times, at least once, and returns
+/// the last path with the cells it closed and the average time taken.
+fn solve<Q: OpenList<Position, Key = usize>>(
+    grid: &Grid,
+    (start, target): (Position, Position),
+    diagonal: bool,
+    runs: u32,
+    open: impl Fn() -> Q,
+) -> (Vec<Position>, usize, Duration) {
+    let run = || {
+        let then = Instant::now();
+        let mut a_star = AStar::<usize, Q>::with_open_list(start, target, diagonal, open());
+        a_star.corner_cutting = CornerCutting::Always;
+        let path = a_star.solve(GridType::Full(grid));
+        let closed = a_star.get_open_and_closed_list().1.len();
+        (path, closed, then.elapsed())
+    };
+    let (mut path, mut closed, mut time) = run();
+    for _ in 1..runs {
+        let (last, last_closed, elapsed) = run();
+        path = last;
+        closed = last_closed;
+        time += elapsed;
+    }
+    (path, closed, time / runs.max(1))
+}
+
+/// The cost of walking from `target` through `path` to `start`.
+fn path_cost(path: &[Position], (start, target): (Position, Position)) -> usize {
+    let mut route = vec![target];
+    route.extend_from_slice(path);
+    route.push(start);
+    route
+        .windows(2)
+        .filter_map(|step| Direction::between(step[0], step[1]))
+        .map(|dir| dir.g_cost())
+        .sum()
+}
+
+fn total_cost(range: &MovementRange) -> usize {
+    range.iter().map(|(_, reach)| reach.cost).sum()
+}
+
+fn usage() -> ! {
+    eprintln!("open_lists [--size <n>] [--runs <n>] [--seed <n>]");
+    process::exit(1);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let (mut size, mut runs, mut seed) = (1024, 3, 0);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or_else(|| usage())
+        };
+        match arg.as_str() {
+            "--size" => size = value(),
+            "--runs" => runs = u32::try_from(value()).unwrap_or_else(|_| usage()),
+            "--seed" => seed = value() as u64,
+            _ => usage(),
+        }
+    }
+    if runs == 0 {
+        eprintln!("There has to be at least one run!");
+        process::exit(1);
+    }
+    match Grid::cell_count(size, size) {
+        Ok(0) => {
+            eprintln!("The grid needs at least one cell!");
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        _ => (),
+    }
+    println!("map,diagonal,search,open_list,cells,cost,time_ms");
+    // f-costs of A* rise by at most twice the most expensive move per step
+    let span = Direction::NorthEast.g_cost();
+    for generator in &[Generator::Cave, Generator::Division] {
+        let mut grid = Grid::new(size, size);
+        let end_points = generator
+            .generate(&mut grid, seed)
+            .expect("The size was checked to be non-empty!");
+        let (start, _) = end_points;
+        for &diagonal in &[false, true] {
+            let mut expected = None;
+            for open_list in OPEN_LISTS.iter() {
+                let (range, time) = match *open_list {
+                    "PriorityQueue" => flood(&grid, start, diagonal, runs, || {
+                        PriorityQueue::<Position, Reverse<usize>>::new()
+                    }),
+                    "BucketQueue" => flood(&grid, start, diagonal, runs, || BucketQueue::new(span)),
+                    _ => flood(&grid, start, diagonal, runs, RadixHeap::new),
+                };
+                let reached = (range.len(), total_cost(&range));
+                check(open_list, "flood", reached, &mut expected);
+                report(generator, diagonal, "flood", open_list, reached, time);
+            }
+            let mut expected = None;
+            for open_list in OPEN_LISTS.iter() {
+                let (path, closed, time) = match *open_list {
+                    "PriorityQueue" => solve(&grid, end_points, diagonal, runs, || {
+                        PriorityQueue::<Position, Reverse<usize>>::new()
+                    }),
+                    "BucketQueue" => solve(&grid, end_points, diagonal, runs, || {
+                        BucketQueue::new(2 * span)
+                    }),
+                    _ => solve(&grid, end_points, diagonal, runs, RadixHeap::new),
+                };
+                let cost = path_cost(&path, end_points);
+                // ties may be broken differently, so only the cost has to agree
+                check(open_list, "A*", (0, cost), &mut expected);
+                report(
+                    generator,
+                    diagonal,
+                    "astar",
+                    open_list,
+                    (closed, cost),
+                    time,
+                );
+            }
+        }
+    }
+}
+
+/// Exits if `found`, the cells reached and their cost, differs from what the
+/// first open list found.
+fn check(
+    open_list: &str,
+    search: &str,
+    found: (usize, usize),
+    expected: &mut Option<(usize, usize)>,
+) {
+    if *expected.get_or_insert(found) != found {
+        eprintln!(
+            "{} {} found {:?} cells and cost instead of {:?}!",
+            search, open_list, found, expected
+        );
+        process::exit(1);
+    }
+}
+
+fn report(
+    generator: &Generator,
+    diagonal: bool,
+    search: &str,
+    open_list: &str,
+    (cells, cost): (usize, usize),
+    time: Duration,
+) {
+    println!(
+        "{:?},{},{},{},{},{},{:.2}",
+        generator,
+        diagonal,
+        search,
+        open_list,
+        cells,
+        cost,
+        time.as_secs_f64() * 1000.
+    );
+}
@@ -0,0 +1,421 @@
+//! Runs a MovingAI `.scen` benchmark through every solver and configuration
+//! and checks the returned path cost against the optimal length in the file.
+//!
+//! ```text
+//! scenario <file.scen> [--maps <dir>] [--limit <n>] [--csv]
+//! ```
+//!
+//! The scenario's optimal lengths assume octile movement that never cuts a
+//! corner, so they are only used for diagonal searches, which run with
+//! `CornerCutting::Never`. Searches without diagonals are checked against
+//! a 4-connected Dijkstra over the same map instead.
+//!
+//! `AStar` and `ParallelAStar` paths are walked in the order returned. The
+//! bidirectional solver's halves aren't ordered, so its cost is that of the
+//! cheapest route through the returned cells, a lower bound on the real one.
+use a_star_graph::{
+    AStar, AStarBidirectional, AStarConfig, AStarTrait, Algorithm, Cell, CornerCutting, Grid,
+    GridType, ParallelAStar, Position, Stencil, Weighting,
+};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+const TOLERANCE: f64 = 1e-4;
+const CORNER_CUTTING: CornerCutting = CornerCutting::Never;
+
+struct Scenario {
+    bucket: usize,
+    map: String,
+    dimension: (usize, usize),
+    start: Position,
+    target: Position,
+    optimal: f64,
+}
+
+fn parse_scen(text: &str) -> Result<Vec<Scenario>, String> {
+    let mut scenarios = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        if line.trim().is_empty() || line.starts_with("version") {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 9 {
+            return Err(format!(
+                "Line {}: expected 9 tab separated fields but found {}!",
+                n,
+                fields.len()
+            ));
+        }
+        let int = |i: usize| {
+            fields[i]
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Line {}: `{}` is not a valid number!", n, fields[i]))
+        };
+        let optimal = fields[8]
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Line {}: `{}` is not a valid length!", n, fields[8]))?;
+        scenarios.push(Scenario {
+            bucket: int(0)?,
+            map: fields[1].to_string(),
+            dimension: (int(2)?, int(3)?),
+            start: Position::new(int(4)?, int(5)?),
+            target: Position::new(int(6)?, int(7)?),
+            optimal,
+        });
+    }
+    Ok(scenarios)
+}
+
+fn load_map(maps: &Path, name: &str) -> Result<Grid, String> {
+    let mut candidates = vec![maps.join(name)];
+    if let Some(file_name) = Path::new(name).file_name() {
+        candidates.push(maps.join(file_name));
+    }
+    for path in candidates.iter() {
+        if let Ok(text) = fs::read_to_string(path) {
+            return Grid::from_map(&text).map_err(|e| format!("{}: {}", path.display(), e));
+        }
+    }
+    Err(format!("Couldn't find map {} in {}!", name, maps.display()))
+}
+
+#[derive(Clone, Copy)]
+enum Solver {
+    AStar,
+    ParallelAStar,
+    Bidirectional {
+        multithreaded: bool,
+        bidirectional: bool,
+    },
+}
+
+impl Solver {
+    fn name(&self) -> &'static str {
+        match self {
+            Solver::AStar => "AStar",
+            Solver::ParallelAStar => "ParallelAStar",
+            Solver::Bidirectional { .. } => "AStarBidirectional",
+        }
+    }
+    fn flags(&self) -> (bool, bool) {
+        match self {
+            Solver::AStar => (false, false),
+            Solver::ParallelAStar => (true, false),
+            Solver::Bidirectional {
+                multithreaded,
+                bidirectional,
+            } => (*multithreaded, *bidirectional),
+        }
+    }
+    /// Whether the returned path is one ordered chain that can be walked.
+    fn ordered(&self) -> bool {
+        !matches!(self, Solver::Bidirectional { .. })
+    }
+    fn run(&self, grid: &Grid, scen: &Scenario, diagonal: bool) -> (Vec<Position>, usize) {
+        let grid_type = GridType::Full(grid);
+        match self {
+            Solver::AStar => {
+                let mut a_star = AStar::new(scen.start, scen.target, diagonal);
+                a_star.corner_cutting = CORNER_CUTTING;
+                let path = a_star.solve(grid_type);
+                (path, a_star.get_open_and_closed_list().1.len())
+            }
+            Solver::ParallelAStar => {
+                let mut a_star = ParallelAStar::new(scen.start, scen.target, diagonal);
+                a_star.corner_cutting = CORNER_CUTTING;
+                let path = a_star.solve(grid_type);
+                (path, a_star.get_open_and_closed_list().1.len())
+            }
+            Solver::Bidirectional {
+                multithreaded,
+                bidirectional,
+            } => {
+                let mut a_star = AStarBidirectional::new(AStarConfig {
+                    start: scen.start,
+                    target: scen.target,
+                    diagonal,
+                    multithreaded: *multithreaded,
+                    bidirectional: *bidirectional,
+                    weighting: Weighting::Exact,
+                    algorithm: Algorithm::AStar,
+                    corner_cutting: CORNER_CUTTING,
+                    stencil: Stencil::Compass,
+                    turns: None,
+                });
+                let path = a_star.solve(grid_type);
+                (path, a_star.get_open_and_closed_list().1.len())
+            }
+        }
+    }
+}
+
+fn configurations() -> Vec<(Solver, bool)> {
+    let mut configs = Vec::new();
+    for &diagonal in &[false, true] {
+        configs.push((Solver::AStar, diagonal));
+        configs.push((Solver::ParallelAStar, diagonal));
+        for &multithreaded in &[false, true] {
+            for &bidirectional in &[false, true] {
+                let solver = Solver::Bidirectional {
+                    multithreaded,
+                    bidirectional,
+                };
+                configs.push((solver, diagonal));
+            }
+        }
+    }
+    configs
+}
+
+/// The octile cost of moving between neighbouring `from` and `to`, or `None`
+/// if the grid doesn't allow that move.
+fn step_cost(grid: &Grid, from: Position, to: Position, diagonal: bool) -> Option<f64> {
+    let open = |x: usize, y: usize| match grid.terrain(Position::new(x, y)) {
+        Some(cell) => cell != Cell::Block,
+        None => false,
+    };
+    let (dx, dy) = (from.x.abs_diff(to.x), from.y.abs_diff(to.y));
+    match (dx, dy) {
+        (1, 0) | (0, 1) if open(to.x, to.y) => Some(1.),
+        (1, 1)
+            if diagonal
+                && open(to.x, to.y)
+                && CORNER_CUTTING.allows((open(from.x, to.y), open(to.x, from.y))) =>
+        {
+            Some(std::f64::consts::SQRT_2)
+        }
+        _ => None,
+    }
+}
+
+/// The cost of walking `[target, path.., start]` in the order returned, or
+/// `None` if a step isn't a legal move.
+fn path_cost(grid: &Grid, scen: &Scenario, path: &[Position], diagonal: bool) -> Option<f64> {
+    if scen.start == scen.target {
+        return Some(0.);
+    }
+    let mut route = vec![scen.target];
+    route.extend_from_slice(path);
+    route.push(scen.start);
+    route
+        .windows(2)
+        .map(|step| step_cost(grid, step[0], step[1], diagonal))
+        .sum()
+}
+
+/// `AStarBidirectional` returns its two chains one after the other, so the
+/// steps can't be walked in order. This is the cost of the cheapest route
+/// through only the returned cells instead, a lower bound on the real one.
+fn lower_bound_cost(
+    grid: &Grid,
+    scen: &Scenario,
+    path: &[Position],
+    diagonal: bool,
+) -> Option<f64> {
+    let mut cells = vec![scen.start];
+    cells.extend_from_slice(path);
+    cells.push(scen.target);
+    let mut best = vec![f64::INFINITY; cells.len()];
+    let mut done = vec![false; cells.len()];
+    best[0] = 0.;
+    while let Some(i) = (0..cells.len())
+        .filter(|i| !done[*i] && best[*i].is_finite())
+        .min_by(|a, b| best[*a].total_cmp(&best[*b]))
+    {
+        if cells[i] == scen.target {
+            return Some(best[i]);
+        }
+        done[i] = true;
+        for j in 0..cells.len() {
+            if let Some(cost) = step_cost(grid, cells[i], cells[j], diagonal) {
+                best[j] = best[j].min(best[i] + cost);
+            }
+        }
+    }
+    None
+}
+
+fn expected_cost(grid: &Grid, scen: &Scenario, diagonal: bool) -> Option<f64> {
+    if diagonal {
+        Some(scen.optimal)
+    } else {
+        grid.reachable(scen.start, usize::MAX, false, CORNER_CUTTING)
+            .cost(&scen.target)
+            .map(|cost| (cost / 10) as f64)
+    }
+}
+
+#[derive(Default)]
+struct Summary {
+    queries: usize,
+    mismatches: usize,
+    expansions: usize,
+    time: Duration,
+}
+
+fn usage() -> ! {
+    eprintln!("scenario <file.scen> [--maps <dir>] [--limit <n>] [--csv]");
+    process::exit(1);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let (mut scen_path, mut maps, mut limit, mut csv) = (None, None, None, false);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--maps" => maps = Some(PathBuf::from(args.next().unwrap_or_else(|| usage()))),
+            "--limit" => {
+                limit = Some(
+                    args.next()
+                        .and_then(|n| n.parse::<usize>().ok())
+                        .unwrap_or_else(|| usage()),
+                )
+            }
+            "--csv" => csv = true,
+            _ if scen_path.is_none() => scen_path = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+    let scen_path = scen_path.unwrap_or_else(|| usage());
+    let maps = maps.unwrap_or_else(|| {
+        scen_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    });
+    let text = fs::read_to_string(&scen_path).unwrap_or_else(|e| {
+        eprintln!("Couldn't read {}: {}", scen_path.display(), e);
+        process::exit(1);
+    });
+    let mut scenarios = parse_scen(&text).unwrap_or_else(|e| {
+        eprintln!("{}: {}", scen_path.display(), e);
+        process::exit(1);
+    });
+    if let Some(limit) = limit {
+        scenarios.truncate(limit);
+    }
+
+    let mut grids: HashMap<String, Grid> = HashMap::new();
+    let configs = configurations();
+    let mut summaries: Vec<Summary> = configs.iter().map(|_| Summary::default()).collect();
+    let mut mismatches = Vec::new();
+    if csv {
+        println!("query,bucket,solver,diagonal,multithreaded,bidirectional,expected,cost,cost_is_lower_bound,ok,expansions,time_us");
+    }
+    for (i, scen) in scenarios.iter().enumerate() {
+        if !grids.contains_key(&scen.map) {
+            let grid = load_map(&maps, &scen.map).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            grids.insert(scen.map.clone(), grid);
+        }
+        let grid = &grids[&scen.map];
+        if grid.dimension() != scen.dimension {
+            eprintln!(
+                "Query {}: map {} is {:?} but the scenario expects {:?}!",
+                i,
+                scen.map,
+                grid.dimension(),
+                scen.dimension
+            );
+            process::exit(1);
+        }
+        for ((solver, diagonal), summary) in configs.iter().zip(summaries.iter_mut()) {
+            let then = Instant::now();
+            let (path, expansions) = solver.run(grid, scen, *diagonal);
+            let time = then.elapsed();
+            let expected = expected_cost(grid, scen, *diagonal);
+            let cost = if solver.ordered() {
+                path_cost(grid, scen, &path, *diagonal)
+            } else {
+                lower_bound_cost(grid, scen, &path, *diagonal)
+            };
+            let ok = match (expected, cost) {
+                (Some(expected), Some(cost)) => (expected - cost).abs() < TOLERANCE,
+                (None, None) => true,
+                _ => false,
+            };
+            summary.queries += 1;
+            summary.expansions += expansions;
+            summary.time += time;
+            let (multithreaded, bidirectional) = solver.flags();
+            let show = |cost: Option<f64>| cost.map_or("-".to_string(), |c| format!("{:.4}", c));
+            if !ok {
+                summary.mismatches += 1;
+                mismatches.push(format!(
+                    "query {} ({} -> {}): {} diagonal={} multithreaded={} bidirectional={} expected {} got {}{}",
+                    i,
+                    scen.start,
+                    scen.target,
+                    solver.name(),
+                    diagonal,
+                    multithreaded,
+                    bidirectional,
+                    show(expected),
+                    if solver.ordered() { "" } else { "at least " },
+                    show(cost)
+                ));
+            }
+            if csv {
+                println!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{}",
+                    i,
+                    scen.bucket,
+                    solver.name(),
+                    diagonal,
+                    multithreaded,
+                    bidirectional,
+                    show(expected),
+                    show(cost),
+                    !solver.ordered(),
+                    ok,
+                    expansions,
+                    time.as_micros()
+                );
+            }
+        }
+    }
+    if csv {
+        return;
+    }
+    println!(
+        "{:<20} {:>8} {:>13} {:>13} {:>8} {:>10} {:>12} {:>10}",
+        "solver",
+        "diagonal",
+        "multithreaded",
+        "bidirectional",
+        "queries",
+        "mismatches",
+        "expansions",
+        "time (ms)"
+    );
+    for ((solver, diagonal), summary) in configs.iter().zip(summaries.iter()) {
+        let (multithreaded, bidirectional) = solver.flags();
+        println!(
+            "{:<20} {:>8} {:>13} {:>13} {:>8} {:>10} {:>12} {:>10}",
+            solver.name(),
+            diagonal,
+            multithreaded,
+            bidirectional,
+            summary.queries,
+            summary.mismatches,
+            summary.expansions,
+            summary.time.as_millis()
+        );
+    }
+    println!("\nAStarBidirectional costs are lower bounds, its path can't be walked in order.");
+    if !mismatches.is_empty() {
+        println!("\nMismatches:");
+        for each in mismatches.iter() {
+            println!("{}", each);
+        }
+    }
+}
@@ -1,25 +1,53 @@
-use a_star_graph::{AStarBidirectional, GridSet, GridType, Request, Response};
-use std::collections::HashSet;
-use std::iter::FromIterator;
+use a_star_graph::{
+    AStarBidirectional, Algorithm, AnytimeAStar, FlowField, Grid, GridType, HeadingAStar, Hpa,
+    HybridAStar, Request, Response, SaveFile, Stencil, Weighting,
+};
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
+use warp::http::StatusCode;
 use warp::Filter;
 
-fn solve(request: Request) -> String {
+fn reply(result: Result<String, String>) -> warp::reply::WithStatus<String> {
+    match result {
+        Ok(body) => warp::reply::with_status(body, StatusCode::OK),
+        Err(err) => {
+            println!("Rejected request: {}", err);
+            warp::reply::with_status(err, StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+fn solve(request: Request) -> Result<String, String> {
     let graph = request.a_star;
+    let grid = request.grid_set()?;
     let mut msg = format!(
         "{} -> {} \nBlockades: {}",
         graph.start,
         graph.target,
-        request.blocked.len()
+        grid.set.len()
     );
-    let set = HashSet::from_iter(request.blocked);
-    let grid = GridSet {
-        width: request.dimension.0,
-        height: request.dimension.1,
-        set,
-    };
+    if let (Some(generator), Some(seed)) = (request.generator, request.seed) {
+        msg.push_str(&format!("\n{:?} maze from seed {}", generator, seed));
+    }
+    match graph.turns {
+        Some(turns) => msg.push_str(&format!(
+            "\nA* over headings, turning by 45°/90°/135° costs {}/{}/{}, {}",
+            turns.slight,
+            turns.square,
+            turns.sharp,
+            match turns.reverse {
+                Some(cost) => format!("reversing {}", cost),
+                None => "no reversing".into(),
+            }
+        )),
+        None => msg.push_str(&format!("\n{}", graph.algorithm.name())),
+    }
+    if graph.stencil != Stencil::Compass {
+        msg.push_str(&format!("\nStencil: {}", graph.stencil.name()));
+    }
     if graph.diagonal {
-        msg.push_str("\nWith diagonal search")
+        msg.push_str("\nWith diagonal search, ");
+        msg.push_str(&graph.corner_cutting.name().to_lowercase());
     }
     if graph.bidirectional {
         msg.push_str("\nWith bidirectional search")
@@ -32,19 +60,182 @@ fn solve(request: Request) -> String {
     } else {
         msg.push_str("\nSingle threaded.")
     }
-    let mut a_s = AStarBidirectional::new(graph);
+    // only A* has a heuristic to weigh, and the search over headings doesn't
+    let weighting = match graph.algorithm {
+        Algorithm::AStar if graph.turns.is_none() => graph.weighting,
+        _ => Weighting::Exact,
+    };
+    match weighting {
+        Weighting::Exact => (),
+        Weighting::Weighted(epsilon) => {
+            msg.push_str(&format!("\nWeighted A* with epsilon {}", epsilon))
+        }
+        Weighting::Anytime {
+            epsilon, millis, ..
+        } => msg.push_str(&format!(
+            "\nARA* from epsilon {} for up to {}ms",
+            epsilon, millis
+        )),
+    }
     let then = Instant::now();
-    let path = a_s.solve(GridType::Set(&grid));
+    let regions = grid.regions(graph.diagonal && graph.corner_cutting.connects_diagonally());
+    // regions follow the compass, other stencils may jump between them
+    let reachable =
+        graph.stencil != Stencil::Compass || regions.connected(graph.start, graph.target);
+    if !reachable {
+        msg.push_str("\nUnreachable, the end points are in different regions.");
+    }
+    let mut headings = Vec::new();
+    let (path, open, closed, bound) = if let Some(turns) = graph.turns {
+        let mut search = HeadingAStar::new(graph, turns);
+        let solution = if reachable {
+            search.solve(GridType::Set(&grid))
+        } else {
+            None
+        };
+        let (open, closed) = search.get_open_and_closed_list();
+        match solution {
+            Some(solution) => {
+                msg.push_str(&format!(
+                    "\nCost: {}, turning {} times",
+                    solution.cost, solution.turns
+                ));
+                let path = solution.path();
+                headings = solution.steps;
+                (path, open, closed, None)
+            }
+            None => (Vec::new(), open, closed, None),
+        }
+    } else if weighting == Weighting::Exact {
+        let mut a_s = AStarBidirectional::new(graph);
+        let path = if reachable {
+            a_s.solve(GridType::Set(&grid))
+        } else {
+            Vec::new()
+        };
+        let (open, closed) = a_s.get_open_and_closed_list();
+        (path, open, closed, None)
+    } else {
+        let mut ara = AnytimeAStar::new(graph);
+        let solution = if reachable {
+            ara.solve(GridType::Set(&grid))
+        } else {
+            None
+        };
+        let (open, closed) = ara.get_open_and_closed_list();
+        match solution {
+            Some(solution) => {
+                msg.push_str(&format!(
+                    "\nCost: {}, at most {:.2} times the optimum",
+                    solution.cost, solution.bound
+                ));
+                (solution.path, open, closed, Some(solution.bound))
+            }
+            None => (Vec::new(), open, closed, None),
+        }
+    };
     let time = then.elapsed().as_millis() as usize;
     println!("{}\nTook: {}ms", msg, time);
-    let (open, closed) = a_s.get_open_and_closed_list();
     let response = Response {
         path,
         time,
         open,
         closed,
+        bound,
+        headings,
     };
-    serde_json::to_string(&response).unwrap()
+    Ok(serde_json::to_string(&response).unwrap())
+}
+
+fn flow_field(request: Request) -> Result<String, String> {
+    let graph = request.a_star;
+    let grid = request.grid_set()?;
+    let then = Instant::now();
+    let field = FlowField::new(
+        GridType::Set(&grid),
+        graph.target,
+        graph.diagonal,
+        graph.corner_cutting,
+    );
+    if let (Some(generator), Some(seed)) = (request.generator, request.seed) {
+        println!("{:?} maze from seed {}", generator, seed);
+    }
+    println!(
+        "Flow field towards {}\nTook: {}ms",
+        graph.target,
+        then.elapsed().as_millis()
+    );
+    Ok(serde_json::to_string(&field).unwrap())
+}
+
+const CLUSTER_SIZE: usize = 16;
+
+/// Solves with HPA*, reusing the abstraction built for the previous request
+/// and only recomputing the clusters whose walls changed since.
+fn hpa_solve(request: Request, hpa: &Mutex<Option<Hpa>>) -> Result<String, String> {
+    let config = request.a_star;
+    let grid = Grid::from(&request.grid_set()?);
+    let then = Instant::now();
+    let mut hpa = hpa.lock().unwrap();
+    match &mut *hpa {
+        Some(hpa)
+            if (hpa.diagonal, hpa.corner_cutting) == (config.diagonal, config.corner_cutting) =>
+        {
+            hpa.sync(&grid)
+        }
+        _ => {
+            *hpa = Some(Hpa::new(
+                &grid,
+                CLUSTER_SIZE,
+                config.diagonal,
+                config.corner_cutting,
+            ))
+        }
+    }
+    let hpa = hpa.as_ref().unwrap();
+    let path = hpa.solve(config.start, config.target);
+    let time = then.elapsed().as_millis() as usize;
+    println!(
+        "{} -> {} \nHPA* over {} abstract nodes\nTook: {}ms",
+        config.start,
+        config.target,
+        hpa.len(),
+        time
+    );
+    let response = Response {
+        path,
+        time,
+        ..Default::default()
+    };
+    Ok(serde_json::to_string(&response).unwrap())
+}
+
+/// Plans a drivable path for the requested vehicle, answering with its poses
+/// or `null` when there is none.
+fn hybrid_solve(request: Request) -> Result<String, String> {
+    let config = request.a_star;
+    let vehicle = request.vehicle.unwrap_or_default();
+    let grid = request.grid_set()?;
+    let then = Instant::now();
+    let path = HybridAStar::new(config, vehicle)?.solve(GridType::Set(&grid));
+    let mut msg = format!(
+        "{} -> {} \nHybrid A* with a turning radius of {} cells",
+        config.start, config.target, vehicle.turning_radius
+    );
+    match &path {
+        Some(path) => msg.push_str(&format!(
+            "\nLength: {:.1} cells over {} poses",
+            path.length,
+            path.poses.len()
+        )),
+        None => msg.push_str("\nNo drivable path."),
+    }
+    println!("{}\nTook: {}ms", msg, then.elapsed().as_millis());
+    Ok(serde_json::to_string(&path).unwrap())
+}
+
+fn load_save(bytes: &[u8]) -> Result<String, String> {
+    solve(SaveFile::load(bytes)?.request())
 }
 
 #[tokio::main]
@@ -57,10 +248,28 @@ async fn main() {
         .allow_any_origin()
         .allow_methods(vec!["POST"])
         .allow_header("content-type");
-    let solve = warp::any()
+    let flow = warp::path("flow")
+        .and(warp::post())
+        .and(warp::body::json())
+        .map(|request| reply(flow_field(request)));
+    let hpa = Arc::new(Mutex::new(None));
+    let hpa = warp::path("hpa")
+        .and(warp::post())
+        .and(warp::body::json())
+        .map(move |request| reply(hpa_solve(request, &hpa)));
+    let hybrid = warp::path("hybrid")
+        .and(warp::post())
+        .and(warp::body::json())
+        .map(|request| reply(hybrid_solve(request)));
+    let save = warp::path("save")
+        .and(warp::post())
+        .and(warp::body::bytes())
+        .map(|bytes: warp::hyper::body::Bytes| reply(load_save(&bytes)));
+    let solve = warp::path::end()
         .and(warp::post())
         .and(warp::body::json())
-        .map(solve)
-        .with(cors);
-    warp::serve(solve).run(([127, 0, 0, 1], 8000)).await;
+        .map(|request| reply(solve(request)));
+    warp::serve(flow.or(hpa).or(hybrid).or(save).or(solve).with(cors))
+        .run(([127, 0, 0, 1], 8000))
+        .await;
 }
@@ -1,15 +1,20 @@
 use crate::{
     dom::{
-        add_event, add_event_mut, add_style, body, document, event_as_input, fetch_then, for_each,
-        get_el, get_target_el, get_value, html_el_from, insert_html_at, loop_animation_frame,
-        query_els, window, FetchMethod, HtmlPosition,
+        add_event, add_event_mut, add_style, body, document, download, event_as_input,
+        event_as_select, fetch_then, for_each, get_el, get_target_el, get_value, html_el_from,
+        insert_html_at, loop_animation_frame, query_els, read_file_bytes_then, read_file_then,
+        set_checked, set_selected, set_value, window, FetchMethod, HtmlPosition,
     },
     DrawMode, RcCell, Renderer,
 };
-use a_star_graph::{AStarBidirectional, AStarConfig, Cell, Grid, Position, Request, Response};
-use js_sys::Math;
+use a_star_graph::{
+    AStarBidirectional, AStarConfig, Algorithm, Blocked, Cell, CornerCutting, FlowField, Generator,
+    Grid, HybridPath, ImageConfig, Position, Regions, Request, Response, Rng, SaveFile, Stencil,
+    TurnCosts, Vehicle, Weighting,
+};
 use maud::html;
 use std::str::FromStr;
+use strum::IntoEnumIterator;
 use strum_macros::EnumString;
 use wasm_bindgen::JsCast;
 use web_sys::{DomStringMap, HtmlCanvasElement, MouseEvent};
@@ -21,14 +26,30 @@ pub enum AppEvent {
     TraceResponse,
     Play,
     Solve,
+    Range,
+    Flow,
+    Drive,
+    Generate(Generator),
     Diagonal(bool),
     Multithreaded(bool),
     Bidirectional(bool),
+    Regions(bool),
+    Anytime(bool),
+    Turns(bool),
+    Algorithm(Algorithm),
+    CornerCutting(CornerCutting),
+    Stencil(Box<Stencil>),
     Step,
     Clear,
     ClearAll,
     Resize,
     ResizeGrid,
+    Seed,
+    LoadMap(String),
+    LoadImage(Vec<u8>),
+    LoadSave(Vec<u8>),
+    SaveJson,
+    SaveBinary,
     None,
 }
 
@@ -39,7 +60,16 @@ pub struct App {
     graph: AStarBidirectional,
     event: RcCell<AppEvent>,
     response: RcCell<Response>,
+    flow: RcCell<Option<FlowField>>,
+    drive: RcCell<Option<HybridPath>>,
+    regions: Regions,
+    show_regions: bool,
+    rng: Rng,
+    /// The generator and seed of the maze on screen, until its walls change.
+    generated: Option<(Generator, u64)>,
     multithreaded: bool,
+    anytime: bool,
+    turns: Option<TurnCosts>,
     solved: bool,
 }
 
@@ -49,6 +79,7 @@ impl App {
         grid: Grid,
         graph: AStarBidirectional,
         renderer: Renderer,
+        rng: Rng,
     ) -> Self {
         document().set_title("A Star Exploration");
         add_style(
@@ -59,7 +90,7 @@ impl App {
                 justify-content: space-around;
                 margin: 5px 0;
             }
-            .bar p, button, input[type=number], input[type=checkbox] + label::before {
+            .bar p, button, select, input[type=number], input[type=checkbox] + label::before {
                 display: inline-block;
                 background-color: #333;
                 border-radius: 5px;
@@ -116,17 +147,54 @@ impl App {
                     button data-event="Play" { "Play" }
                     button data-event="Step" { "Step" }
                     button data-event="Solve" { "Solve" }
+                    button data-event="Range" { "Range" }
+                    input id="budget" min="0" value="50" type="number" {}
+                    button data-event="Flow" { "Flow" }
+                    button data-event="Drive" { "Drive" }
+                    input id="radius" min="0.5" step="0.5" value="3" type="number" {}
+                    label for="epsilon" { "ε" }
+                    input id="epsilon" min="1" step="0.1" value="1" type="number" {}
+                    input id="anytime" type="checkbox" {}
+                    label for="anytime" {"Anytime"}
+                    input id="turns" type="checkbox" {}
+                    label for="turns" {"Turn costs"}
                     span#time {}
                 }
                 .center {
+                    select id="algorithm" {
+                        @for algorithm in Algorithm::iter() {
+                            option value=(format!("{:?}", algorithm)) { (algorithm.name()) }
+                        }
+                    }
                     input id="multi" type="checkbox" {}
                     label for="multi" {"Multithreaded"}
                     input id="bi" type="checkbox" {}
                     label for="bi" {"Bi-directional"}
                     input id="diag" type="checkbox" {}
                     label for="diag" {"Diagonal"}
+                    select id="corner-cutting" {
+                        @for corner_cutting in CornerCutting::iter() {
+                            option value=(format!("{:?}", corner_cutting)) { (corner_cutting.name()) }
+                        }
+                    }
+                    select id="stencil" {
+                        @for stencil in Stencil::iter() {
+                            @if let Stencil::Custom(_) = stencil {
+                                option value=(stencil.as_ref()) disabled { (stencil.name()) }
+                            } @else {
+                                option value=(stencil.as_ref()) { (stencil.name()) }
+                            }
+                        }
+                    }
+                    input id="regions" type="checkbox" {}
+                    label for="regions" {"Regions"}
                 }
                 .right {
+                    button data-generator="Backtracker" { "Backtracker" }
+                    button data-generator="Prim" { "Prim" }
+                    button data-generator="Kruskal" { "Kruskal" }
+                    button data-generator="Division" { "Division" }
+                    button data-generator="Cave" { "Cave" }
                     button data-event="Clear" { "Clear" }
                     button data-event="ClearAll" { "Clear All" }
                 }
@@ -140,6 +208,16 @@ impl App {
                     p{"Drag start/end position"}
                 }
                 .center {
+                    label { "Seed" }
+                    input id="seed" data-event="Seed" min="0" value=(rng.seed()) type="number" {}
+                    label for="map" { "Load .map" }
+                    input id="map" type="file" accept=".map" {}
+                    label for="image" { "Load image" }
+                    input id="image" type="file" accept="image/png" {}
+                    button data-event="SaveJson" { "Save" }
+                    button data-event="SaveBinary" { "Save binary" }
+                    label for="save" { "Load save" }
+                    input id="save" type="file" accept=".json,.astar" {}
                 }
                 .right {
                     label { "Grid Size" }
@@ -156,6 +234,7 @@ impl App {
             HtmlPosition::End,
         );
         let event = RcCell::new(AppEvent::Resize);
+        let regions = grid.regions(graph.diagonal && graph.corner_cutting.connects_diagonally());
         let app = Self {
             grid,
             graph,
@@ -163,7 +242,15 @@ impl App {
             canvas,
             event,
             response: RcCell::new(Default::default()),
+            flow: RcCell::new(None),
+            drive: RcCell::new(None),
+            regions,
+            show_regions: false,
+            rng,
+            generated: None,
             multithreaded: false,
+            anytime: false,
+            turns: None,
             solved: false,
         };
         app.bind_events();
@@ -193,6 +280,41 @@ impl App {
                 *ev.borrow_mut() = event;
             });
         });
+        let ev = self.event.clone();
+        for_each(&query_els("button[data-generator]"), move |each| {
+            let ev = ev.clone();
+            add_event(&each, "click", move |e| {
+                let generator = Generator::from_str(
+                    &html_el_from(get_target_el(&e))
+                        .dataset()
+                        .get("generator")
+                        .unwrap(),
+                )
+                .unwrap();
+                *ev.borrow_mut() = AppEvent::Generate(generator);
+            });
+        });
+        let ev = self.event.clone();
+        add_event(&get_el("map"), "change", move |e| {
+            let ev = ev.clone();
+            read_file_then(&event_as_input(&e), move |text| {
+                ev.mutate(AppEvent::LoadMap(text));
+            });
+        });
+        let ev = self.event.clone();
+        add_event(&get_el("image"), "change", move |e| {
+            let ev = ev.clone();
+            read_file_bytes_then(&event_as_input(&e), move |bytes| {
+                ev.mutate(AppEvent::LoadImage(bytes));
+            });
+        });
+        let ev = self.event.clone();
+        add_event(&get_el("save"), "change", move |e| {
+            let ev = ev.clone();
+            read_file_bytes_then(&event_as_input(&e), move |bytes| {
+                ev.mutate(AppEvent::LoadSave(bytes));
+            });
+        });
         add_event_mut(&get_el("diag"), "input", &self.event, |event, e| {
             *event = AppEvent::Diagonal(event_as_input(&e).checked());
         });
@@ -202,6 +324,35 @@ impl App {
         add_event_mut(&get_el("multi"), "input", &self.event, |event, e| {
             *event = AppEvent::Multithreaded(event_as_input(&e).checked());
         });
+        add_event_mut(&get_el("algorithm"), "input", &self.event, |event, e| {
+            if let Ok(algorithm) = Algorithm::from_str(&event_as_select(&e).value()) {
+                *event = AppEvent::Algorithm(algorithm);
+            }
+        });
+        add_event_mut(
+            &get_el("corner-cutting"),
+            "input",
+            &self.event,
+            |event, e| {
+                if let Ok(corner_cutting) = CornerCutting::from_str(&event_as_select(&e).value()) {
+                    *event = AppEvent::CornerCutting(corner_cutting);
+                }
+            },
+        );
+        add_event_mut(&get_el("stencil"), "input", &self.event, |event, e| {
+            if let Ok(stencil) = Stencil::from_str(&event_as_select(&e).value()) {
+                *event = AppEvent::Stencil(Box::new(stencil));
+            }
+        });
+        add_event_mut(&get_el("anytime"), "input", &self.event, |event, e| {
+            *event = AppEvent::Anytime(event_as_input(&e).checked());
+        });
+        add_event_mut(&get_el("turns"), "input", &self.event, |event, e| {
+            *event = AppEvent::Turns(event_as_input(&e).checked());
+        });
+        add_event_mut(&get_el("regions"), "input", &self.event, |event, e| {
+            *event = AppEvent::Regions(event_as_input(&e).checked());
+        });
         add_event_mut(&self.canvas, "mousedown", &self.event, |event, e| {
             let me = e.dyn_into::<MouseEvent>().unwrap();
             let button = me.buttons();
@@ -237,42 +388,170 @@ impl App {
             *event = AppEvent::Resize;
         });
     }
+    fn config(&self) -> AStarConfig {
+        let (start, target) = self.graph.end_points();
+        AStarConfig {
+            diagonal: self.graph.diagonal,
+            start,
+            target,
+            multithreaded: self.multithreaded,
+            bidirectional: self.graph.bidirectional(),
+            weighting: self.weighting(),
+            algorithm: self.graph.algorithm(),
+            corner_cutting: self.graph.corner_cutting,
+            stencil: self.graph.stencil,
+            turns: self.turns,
+        }
+    }
+    /// Only solving on the backend honours the weighting and turn costs,
+    /// stepping through a search here runs the chosen algorithm unweighted.
+    fn weighting(&self) -> Weighting {
+        let epsilon = get_value("epsilon").parse().unwrap_or(1_f64).max(1.);
+        if self.anytime {
+            Weighting::Anytime {
+                epsilon,
+                step: 0.5,
+                millis: 100,
+            }
+        } else if epsilon > 1. {
+            Weighting::Weighted(epsilon)
+        } else {
+            Weighting::Exact
+        }
+    }
+    /// The walls are left out of a generated maze, the backend rebuilds them
+    /// from its seed.
+    fn request(&self) -> Request {
+        let blocked = match self.generated {
+            Some(_) => Blocked::default(),
+            None => Blocked::from(&self.grid),
+        };
+        Request {
+            dimension: self.grid.dimension(),
+            blocked,
+            a_star: self.config(),
+            seed: self.generated.map(|(_, seed)| seed),
+            generator: self.generated.map(|(generator, _)| generator),
+            vehicle: Some(self.vehicle()),
+        }
+    }
+    /// A vehicle with the turning radius entered, in cells.
+    fn vehicle(&self) -> Vehicle {
+        let turning_radius = get_value("radius").parse().unwrap_or(3_f64).max(0.5);
+        Vehicle {
+            turning_radius,
+            ..Default::default()
+        }
+    }
+    /// Checks the end points share a region before a search is started, since
+    /// a search between two regions only ends once the open list runs out.
+    /// Regions follow the compass, so other stencils are always let through.
+    fn reachable(&self) -> bool {
+        let (start, target) = self.graph.end_points();
+        let reachable =
+            self.graph.stencil != Stencil::Compass || self.regions.connected(start, target);
+        if !reachable {
+            get_el("time").set_inner_html("Unreachable");
+        }
+        reachable
+    }
+    /// Diagonal moves only connect regions when they may cut between walls.
+    fn update_regions(&mut self) {
+        let diagonal = self.graph.diagonal && self.graph.corner_cutting.connects_diagonally();
+        self.regions = self.grid.regions(diagonal);
+    }
+    /// Headings belong to the path last traced, so they go whenever it does.
+    fn clear_headings(&self) {
+        self.response.borrow_mut().headings.clear();
+    }
+    fn load_save(&mut self, save: SaveFile) {
+        let config = save.config;
+        self.load_grid(save.grid);
+        self.graph.diagonal = config.diagonal;
+        self.graph.set_bidirectional(config.bidirectional);
+        self.multithreaded = config.multithreaded;
+        set_checked("diag", config.diagonal);
+        set_checked("bi", config.bidirectional);
+        self.graph.set_algorithm(config.algorithm);
+        set_selected("algorithm", &format!("{:?}", config.algorithm));
+        self.graph.corner_cutting = config.corner_cutting;
+        set_selected("corner-cutting", &format!("{:?}", config.corner_cutting));
+        self.graph.stencil = config.stencil;
+        set_selected("stencil", config.stencil.as_ref());
+        self.update_regions();
+        set_checked("multi", config.multithreaded);
+        let (epsilon, anytime) = match config.weighting {
+            Weighting::Exact => (1., false),
+            Weighting::Weighted(epsilon) => (epsilon, false),
+            Weighting::Anytime { epsilon, .. } => (epsilon, true),
+        };
+        self.anytime = anytime;
+        set_value("epsilon", &epsilon.to_string());
+        set_checked("anytime", anytime);
+        self.turns = config.turns;
+        set_checked("turns", config.turns.is_some());
+    }
+    fn load_grid(&mut self, grid: Grid) {
+        self.grid = grid;
+        self.generated = None;
+        let (start, target) = match self.grid.end_points() {
+            Some(end_points) => end_points,
+            None => self.grid.set_rand_start_n_end(&mut self.rng),
+        };
+        self.graph.set_start(start);
+        self.graph.set_target(target);
+        set_value("width", &self.grid.width.to_string());
+        set_value("height", &self.grid.height.to_string());
+        self.flow.mutate(None);
+        self.drive.mutate(None);
+        self.clear_headings();
+        self.solved = false;
+        self.renderer.resize(&self.canvas, &self.grid);
+    }
     pub fn start(mut self) {
         loop_animation_frame(
             move |_| {
                 let mut reset = true;
                 let ev = self.event.clone();
-                let mut event = self.event.borrow_mut();
+                let current = self.event.clone();
+                let mut event = current.borrow_mut();
+                self.regions.sync(&self.grid);
                 match &*event {
                     AppEvent::Mouse(old_pos, new_pos, fill) => {
-                        let (row, col) = self.renderer.get_indices(old_pos.x, old_pos.y);
-                        let old_cell = self.grid.get(col, row);
+                        let old_i = self.renderer.get_position(old_pos.x, old_pos.y);
+                        let old_cell = self.grid.get(old_i).unwrap_or(Cell::Block);
                         let drag = if old_cell == Cell::Start || old_cell == Cell::End {
                             true
                         } else {
-                            self.grid.set(row, col, *fill);
+                            if self.grid.contains(old_i) {
+                                self.grid.set(old_i, *fill);
+                                self.generated = None;
+                            }
                             false
                         };
                         if let Some(n_p) = new_pos {
-                            let old_i = Position::new(row, col);
-                            let (row, col) = self.renderer.get_indices(n_p.x, n_p.y);
-                            let new_i = Position::new(row, col);
+                            let new_i = self.renderer.get_position(n_p.x, n_p.y);
                             let (start, target) = self.graph.end_points();
+                            let new_cell = self.grid.get(new_i);
                             if old_i != new_i && new_i != start && new_i != target {
-                                let new_cell = self.grid.get(col, row);
-                                if drag {
-                                    self.grid.set(old_i.x, old_i.y, Cell::Path);
-                                } else {
-                                    self.grid.draw_line(new_i, old_i, *fill);
+                                if let Some(new_cell) = new_cell {
+                                    if !drag || new_cell == Cell::Block {
+                                        self.generated = None;
+                                    }
+                                    if drag {
+                                        self.grid.set(old_i, Cell::Path);
+                                    } else {
+                                        self.grid.draw_line(new_i, old_i, *fill);
+                                    }
+                                    self.grid.set(new_i, old_cell);
+                                    if old_cell == Cell::Start {
+                                        self.graph.set_start(new_i);
+                                    } else if old_cell == Cell::End {
+                                        self.graph.set_target(new_i)
+                                    }
+                                    let new_cell = if drag { new_cell } else { *fill };
+                                    *event = AppEvent::Mouse(*n_p, Some(*n_p), new_cell);
                                 }
-                                self.grid.set(new_i.x, new_i.y, old_cell);
-                                if old_cell == Cell::Start {
-                                    self.graph.set_start(new_i);
-                                } else if old_cell == Cell::End {
-                                    self.graph.set_target(new_i)
-                                }
-                                let new_cell = if drag { new_cell } else { *fill };
-                                *event = AppEvent::Mouse(*n_p, Some(*n_p), new_cell);
                             }
                         } else {
                         }
@@ -286,8 +565,12 @@ impl App {
                             if let Ok(height) = get_value("height").parse() {
                                 if width >= 8 && height >= 8 {
                                     self.grid.resize(width, height);
+                                    self.generated = None;
+                                    self.flow.mutate(None);
+                                    self.drive.mutate(None);
+                                    self.clear_headings();
                                     let (start, target) =
-                                        self.grid.set_rand_start_n_end(&|| Math::random());
+                                        self.grid.set_rand_start_n_end(&mut self.rng);
                                     self.graph.set_start(start);
                                     self.graph.set_target(target);
                                     self.renderer.resize(&self.canvas, &self.grid);
@@ -295,9 +578,53 @@ impl App {
                             }
                         }
                     }
+                    AppEvent::Seed => {
+                        if let Ok(seed) = get_value("seed").parse() {
+                            self.rng = Rng::new(seed);
+                            self.grid = Grid::new(self.grid.width, self.grid.height);
+                            self.generated = None;
+                            let (start, target) = self.grid.set_rand_start_n_end(&mut self.rng);
+                            self.graph.set_start(start);
+                            self.graph.set_target(target);
+                            self.flow.mutate(None);
+                            self.drive.mutate(None);
+                            self.clear_headings();
+                            self.solved = false;
+                        }
+                    }
+                    AppEvent::LoadMap(text) => match Grid::from_map(text) {
+                        Ok(grid) => self.load_grid(grid),
+                        Err(err) => {
+                            crate::log!("Couldn't load the map!\n", err);
+                        }
+                    },
+                    AppEvent::LoadImage(bytes) => {
+                        match Grid::from_png(bytes, &ImageConfig::default()) {
+                            Ok(grid) => self.load_grid(grid),
+                            Err(err) => {
+                                crate::log!("Couldn't load the image!\n", err);
+                            }
+                        }
+                    }
+                    AppEvent::LoadSave(bytes) => match SaveFile::load(bytes) {
+                        Ok(save) => self.load_save(save),
+                        Err(err) => {
+                            crate::log!("Couldn't load the save!\n", err);
+                        }
+                    },
+                    AppEvent::SaveJson => {
+                        let save = SaveFile::new(self.grid.clone(), self.config());
+                        download("grid.json", save.to_json().as_bytes());
+                    }
+                    AppEvent::SaveBinary => {
+                        let save = SaveFile::new(self.grid.clone(), self.config());
+                        download("grid.astar", &save.to_bytes());
+                    }
+                    AppEvent::Play | AppEvent::Step if !self.reachable() => (),
                     AppEvent::Play | AppEvent::Step => {
                         if self.solved {
                             self.grid.clear(false);
+                            self.clear_headings();
                             self.solved = false;
                         }
                         self.graph.step(&mut self.grid);
@@ -319,9 +646,15 @@ impl App {
                         let result = self.response.borrow();
                         for each in result.path.iter() {
                             if self.graph.not_start_nor_end(*each) {
-                                self.grid.set(each.x, each.y, Cell::ShortestPath);
+                                self.grid.set_overlay(*each, Cell::ShortestPath);
                             }
-                            get_el("time").set_inner_html(&format!("{} ms", result.time));
+                            let time = match result.bound {
+                                Some(bound) => {
+                                    format!("{} ms, ≤ {:.2}× optimal", result.time, bound)
+                                }
+                                None => format!("{} ms", result.time),
+                            };
+                            get_el("time").set_inner_html(&time);
                         }
                     }
                     AppEvent::TraceResponse => {
@@ -329,49 +662,109 @@ impl App {
                         let result = self.response.borrow();
                         for each in &result.open {
                             if self.graph.not_start_nor_end(*each) {
-                                self.grid.set(each.x, each.y, Cell::Visiting);
+                                self.grid.set_overlay(*each, Cell::Visiting);
                             }
                         }
                         for each in &result.closed {
                             if self.graph.not_start_nor_end(*each) {
-                                self.grid.set(each.x, each.y, Cell::Visited);
+                                self.grid.set_overlay(*each, Cell::Visited);
                             }
                         }
                         *event = AppEvent::Trace;
                         reset = false;
                     }
+                    AppEvent::Range => {
+                        if let Ok(budget) = get_value("budget").parse() {
+                            self.grid.clear(false);
+                            self.clear_headings();
+                            let (start, _) = self.graph.end_points();
+                            let range = self.grid.reachable(
+                                start,
+                                budget,
+                                self.graph.diagonal,
+                                self.graph.corner_cutting,
+                            );
+                            for each in range.positions() {
+                                if self.graph.not_start_nor_end(*each) {
+                                    self.grid.set_overlay(*each, Cell::Reachable);
+                                }
+                            }
+                            self.solved = true;
+                        }
+                    }
+                    AppEvent::Flow => {
+                        let flow = self.flow.clone();
+                        fetch_then(
+                            "http:///localhost:8000/flow".into(),
+                            FetchMethod::post(&self.request()),
+                            move |field: FlowField| {
+                                flow.mutate(Some(field));
+                            },
+                        );
+                    }
+                    AppEvent::Drive => {
+                        let drive = self.drive.clone();
+                        fetch_then(
+                            "http:///localhost:8000/hybrid".into(),
+                            FetchMethod::post(&self.request()),
+                            move |path: Option<HybridPath>| {
+                                if path.is_none() {
+                                    get_el("time").set_inner_html("Undrivable");
+                                }
+                                drive.mutate(path);
+                            },
+                        );
+                    }
+                    AppEvent::Generate(generator) => {
+                        let seed = self.rng.next_u64();
+                        if let Some((start, target)) = self.grid.generate(*generator, seed) {
+                            self.generated = Some((*generator, seed));
+                            self.graph.set_start(start);
+                            self.graph.set_target(target);
+                        }
+                        self.flow.mutate(None);
+                        self.drive.mutate(None);
+                        self.clear_headings();
+                        self.solved = false;
+                    }
                     AppEvent::Diagonal(diag) => {
                         self.graph.diagonal = *diag;
+                        self.update_regions();
+                    }
+                    AppEvent::Regions(show) => {
+                        self.show_regions = *show;
+                    }
+                    AppEvent::Anytime(anytime) => {
+                        self.anytime = *anytime;
+                    }
+                    AppEvent::Turns(turns) => {
+                        self.turns = if *turns {
+                            Some(TurnCosts::default())
+                        } else {
+                            None
+                        };
                     }
                     AppEvent::Multithreaded(multi) => {
                         self.multithreaded = *multi;
                     }
+                    AppEvent::Algorithm(algorithm) => {
+                        self.graph.set_algorithm(*algorithm);
+                    }
+                    AppEvent::CornerCutting(corner_cutting) => {
+                        self.graph.corner_cutting = *corner_cutting;
+                        self.update_regions();
+                    }
+                    AppEvent::Stencil(stencil) => {
+                        self.graph.stencil = **stencil;
+                    }
                     AppEvent::Bidirectional(bidir) => {
                         self.graph.set_bidirectional(*bidir);
                     }
+                    AppEvent::Solve if !self.reachable() => (),
                     AppEvent::Solve => {
-                        let mut blocked = Vec::new();
-                        let (width, height) = (self.grid.width, self.grid.height);
-                        for (i, each) in self.grid.iter().enumerate() {
-                            let row = i / width;
-                            let column = i % width;
-                            if *each == Cell::Block {
-                                blocked.push(Position::new(column, row));
-                            }
-                        }
+                        let request = self.request();
                         self.grid.clear(false);
-                        let (start, target) = self.graph.end_points();
-                        let request = Request {
-                            dimension: (width, height),
-                            blocked,
-                            a_star: AStarConfig {
-                                diagonal: self.graph.diagonal,
-                                start,
-                                target,
-                                multithreaded: self.multithreaded,
-                                bidirectional: self.graph.bidirectional(),
-                            },
-                        };
+                        self.clear_headings();
                         let res = self.response.clone();
                         fetch_then(
                             "http:///localhost:8000/".into(),
@@ -385,16 +778,35 @@ impl App {
                         );
                     }
                     AppEvent::Clear => {
+                        self.flow.mutate(None);
+                        self.drive.mutate(None);
                         self.graph.clear();
                         self.grid.clear(false);
+                        self.clear_headings();
                     }
                     AppEvent::ClearAll => {
+                        self.flow.mutate(None);
+                        self.drive.mutate(None);
                         self.graph.clear();
                         self.grid.clear(true);
+                        self.generated = None;
+                        self.clear_headings();
                     }
                     _ => (),
                 }
                 self.renderer.draw_grid(&self.grid, DrawMode::Circle);
+                if self.show_regions {
+                    self.regions.sync(&self.grid);
+                    self.renderer.draw_regions(&self.regions);
+                }
+                if let Some(field) = &*self.flow.borrow() {
+                    self.renderer.draw_flow(field);
+                }
+                if let Some(path) = &*self.drive.borrow() {
+                    self.renderer.draw_curve(&path.poses);
+                }
+                self.renderer
+                    .draw_headings(&self.response.borrow().headings);
                 if reset {
                     *event = AppEvent::None;
                 }
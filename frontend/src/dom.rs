@@ -5,8 +5,9 @@ use wasm_bindgen::JsValue;
 use wasm_bindgen::{closure::Closure, JsCast};
 use wasm_bindgen_futures::{future_to_promise, JsFuture};
 use web_sys::{
-    Document, Element, Event, EventTarget, HtmlElement, HtmlHeadElement, HtmlInputElement,
-    HtmlStyleElement, NodeList, Request, RequestInit, Response, Window,
+    Blob, Document, Element, Event, EventTarget, HtmlAnchorElement, HtmlElement, HtmlHeadElement,
+    HtmlInputElement, HtmlSelectElement, HtmlStyleElement, NodeList, Request, RequestInit,
+    Response, Url, Window,
 };
 
 pub fn query_els(selector: &str) -> NodeList {
@@ -52,6 +53,58 @@ pub fn get_value(id: &str) -> String {
         .value()
 }
 
+pub fn set_value(id: &str, value: &str) {
+    get_el(id)
+        .dyn_into::<HtmlInputElement>()
+        .unwrap_or_else(|e| panic!("Element with id {} not an input element!:\n{:#?}", id, e))
+        .set_value(value);
+}
+
+pub fn read_file_then<F: 'static + Fn(String)>(input: &HtmlInputElement, closure: F) {
+    if let Some(file) = input.files().and_then(|files| files.get(0)) {
+        let resolve = Closure::wrap(Box::new(move |text: JsValue| {
+            closure(text.as_string().unwrap_or_default());
+        }) as Box<dyn FnMut(JsValue)>);
+        let _ = file.text().then(&resolve);
+        resolve.forget();
+    }
+}
+
+pub fn read_file_bytes_then<F: 'static + Fn(Vec<u8>)>(input: &HtmlInputElement, closure: F) {
+    if let Some(file) = input.files().and_then(|files| files.get(0)) {
+        let resolve = Closure::wrap(Box::new(move |buffer: JsValue| {
+            closure(js_sys::Uint8Array::new(&buffer).to_vec());
+        }) as Box<dyn FnMut(JsValue)>);
+        let _ = file.array_buffer().then(&resolve);
+        resolve.forget();
+    }
+}
+
+pub fn set_checked(id: &str, checked: bool) {
+    get_el(id)
+        .dyn_into::<HtmlInputElement>()
+        .unwrap_or_else(|e| panic!("Element with id {} not an input element!:\n{:#?}", id, e))
+        .set_checked(checked);
+}
+
+pub fn set_selected(id: &str, value: &str) {
+    get_el(id)
+        .dyn_into::<HtmlSelectElement>()
+        .unwrap_or_else(|e| panic!("Element with id {} not a select element!:\n{:#?}", id, e))
+        .set_value(value);
+}
+
+pub fn download(name: &str, bytes: &[u8]) {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let blob = Blob::new_with_u8_array_sequence(&parts).unwrap();
+    let url = Url::create_object_url_with_blob(&blob).unwrap();
+    let anchor = create_el("a").dyn_into::<HtmlAnchorElement>().unwrap();
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    Url::revoke_object_url(&url).unwrap();
+}
+
 pub fn event_as_input(event: &Event) -> HtmlInputElement {
     event
         .target()
@@ -60,6 +113,14 @@ pub fn event_as_input(event: &Event) -> HtmlInputElement {
         .unwrap()
 }
 
+pub fn event_as_select(event: &Event) -> HtmlSelectElement {
+    event
+        .target()
+        .unwrap()
+        .dyn_into::<HtmlSelectElement>()
+        .unwrap()
+}
+
 pub enum HtmlPosition {
     Before,
     Start,
@@ -1,5 +1,5 @@
 use crate::dom::{add_event, body};
-use a_star_graph::{Cell, Grid};
+use a_star_graph::{Cell, Direction, FlowField, Grid, Heading, Pose, Position, Regions};
 use std::collections::HashMap;
 use strum::IntoEnumIterator;
 use wasm_bindgen::{JsCast, JsValue};
@@ -26,6 +26,9 @@ pub struct Renderer {
     ctx: CanvasRenderingContext2d,
     config: RendererConfig,
     colors: HashMap<Cell, (JsValue, JsValue)>, // caching color names so that wasm doesn't create new string
+    arrow_color: JsValue,
+    heading_color: JsValue,
+    curve_color: JsValue,
     path: Path2d,
 }
 
@@ -62,6 +65,9 @@ impl Renderer {
                 stroke_width,
             },
             colors,
+            arrow_color: JsValue::from("#ddd"),
+            heading_color: JsValue::from("#f80"),
+            curve_color: JsValue::from("#0af"),
             path,
         }
     }
@@ -99,18 +105,103 @@ impl Renderer {
     pub fn draw_grid(&self, grid: &Grid, draw_mode: DrawMode) {
         self.ctx
             .clear_rect(0., 0., self.config.width as f64, self.config.height as f64);
-        for i in 0..grid.height {
-            for j in 0..grid.width {
-                let (x, y) = self.get_offset(j, i);
-                let cell = grid.get(i, j);
-                let d_m = match cell {
-                    Cell::ShortestPath => DrawMode::Point,
-                    _ => draw_mode,
-                };
-                self.draw_cell(x as f64, y as f64, cell, d_m);
+        for pos in grid.positions() {
+            let (x, y) = self.get_offset(pos);
+            let base = if grid.start() == Some(pos) {
+                Cell::Start
+            } else if grid.end() == Some(pos) {
+                Cell::End
+            } else {
+                grid.terrain(pos).unwrap()
+            };
+            match grid.overlay(pos) {
+                Some(Cell::ShortestPath) if !base.is_end_point() => {
+                    self.draw_cell(x, y, base, draw_mode);
+                    self.draw_cell(x, y, Cell::ShortestPath, DrawMode::Point);
+                }
+                Some(overlay) if !base.is_end_point() => {
+                    self.draw_cell(x, y, overlay, draw_mode);
+                }
+                _ => self.draw_cell(x, y, base, draw_mode),
             }
         }
     }
+    pub fn draw_flow(&self, field: &FlowField) {
+        self.ctx
+            .set_line_width((self.config.cell_size / 10.).max(1.));
+        self.ctx.set_stroke_style(&self.arrow_color);
+        self.ctx.begin_path();
+        for i in 0..field.height {
+            for j in 0..field.width {
+                let pos = Position::new(j, i);
+                if let Some(dir) = field.direction(pos) {
+                    self.arrow(pos, dir);
+                }
+            }
+        }
+        self.ctx.stroke();
+    }
+    /// Points an arrow along each step of a path the way it was entered.
+    pub fn draw_headings(&self, headings: &[Heading]) {
+        self.ctx
+            .set_line_width((self.config.cell_size / 6.).max(1.));
+        self.ctx.set_stroke_style(&self.heading_color);
+        self.ctx.begin_path();
+        for each in headings {
+            self.arrow(each.pos, each.direction);
+        }
+        self.ctx.stroke();
+    }
+    /// Joins the poses of a planned drive into a curve.
+    pub fn draw_curve(&self, poses: &[Pose]) {
+        self.ctx
+            .set_line_width((self.config.cell_size / 6.).max(1.));
+        self.ctx.set_stroke_style(&self.curve_color);
+        self.ctx.begin_path();
+        for (i, pose) in poses.iter().enumerate() {
+            let (x, y) = self.get_point(pose.x, pose.y);
+            if i == 0 {
+                self.ctx.move_to(x, y);
+            } else {
+                self.ctx.line_to(x, y);
+            }
+        }
+        self.ctx.stroke();
+    }
+    fn arrow(&self, pos: Position, dir: Direction) {
+        let length = self.config.cell_size * 0.35;
+        let (x, y) = self.get_offset(pos);
+        let r = self.config.cell_size / 2.;
+        let (dx, dy) = dir.offset();
+        let norm = ((dx * dx + dy * dy) as f64).sqrt();
+        let (dx, dy) = (dx as f64 / norm * length, dy as f64 / norm * length);
+        let (cx, cy) = (x + r, y + r);
+        let (tip_x, tip_y) = (cx + dx, cy + dy);
+        self.ctx.move_to(cx - dx, cy - dy);
+        self.ctx.line_to(tip_x, tip_y);
+        // arrow head: two short strokes rotated 135° either side of the shaft
+        let half = std::f64::consts::FRAC_1_SQRT_2;
+        for (sin, cos) in &[(half, -half), (-half, -half)] {
+            self.ctx.move_to(tip_x, tip_y);
+            self.ctx.line_to(
+                tip_x + (dx * cos - dy * sin) / 2.,
+                tip_y + (dx * sin + dy * cos) / 2.,
+            );
+        }
+    }
+    pub fn draw_regions(&self, regions: &Regions) {
+        // spread hues by the golden angle so neighbouring labels stand apart
+        let mut colors = HashMap::new();
+        for (pos, label) in regions.iter() {
+            let color = colors.entry(label).or_insert_with(|| {
+                JsValue::from(format!("hsla({}, 70%, 50%, 0.45)", label * 137 % 360))
+            });
+            let (x, y) = self.get_offset(pos);
+            self.ctx.set_fill_style(color);
+            self.ctx
+                .fill_rect(x, y, self.config.cell_size, self.config.cell_size);
+        }
+    }
     pub fn draw_cell(&self, x: f64, y: f64, cell: Cell, draw_mode: DrawMode) {
         let (fill_color, stroke_color) = self.colors.get(&cell).unwrap();
         self.ctx.set_fill_style(fill_color);
@@ -150,20 +241,27 @@ impl Renderer {
             }
         }
     }
-    fn get_offset(&self, row: usize, column: usize) -> (f64, f64) {
+    fn get_offset(&self, pos: Position) -> (f64, f64) {
         (
             self.config.gap as f64
-                + (row as f64 * (self.config.cell_size + self.config.gap as f64)),
+                + (pos.x as f64 * (self.config.cell_size + self.config.gap as f64)),
             self.config.gap as f64
-                + (column as f64 * (self.config.cell_size + self.config.gap as f64)),
+                + (pos.y as f64 * (self.config.cell_size + self.config.gap as f64)),
         )
     }
-    pub fn get_indices(&self, x: usize, y: usize) -> (usize, usize) {
+    /// The pixel at a point measured in cells, where cell `(x, y)` spans
+    /// `x..x + 1` and `y..y + 1`.
+    fn get_point(&self, x: f64, y: f64) -> (f64, f64) {
+        let pitch = self.config.cell_size + self.config.gap;
+        (self.config.gap + x * pitch, self.config.gap + y * pitch)
+    }
+    /// The grid position under the pixel at `x`, `y` of the canvas.
+    pub fn get_position(&self, x: usize, y: usize) -> Position {
         let calc = |val| {
             let actual_val = val as f64 - self.config.gap;
             let val = actual_val / (self.config.cell_size + self.config.gap);
             val as usize
         };
-        (calc(x), calc(y))
+        Position::new(calc(x), calc(y))
     }
 }
@@ -1,4 +1,6 @@
-use a_star_graph::{AStarBidirectional, AStarConfig, Grid};
+use a_star_graph::{
+    AStarBidirectional, AStarConfig, Algorithm, CornerCutting, Grid, Rng, Stencil, Weighting,
+};
 use js_sys::Math;
 use wasm_bindgen::JsCast;
 use web_sys::HtmlCanvasElement;
@@ -35,15 +37,21 @@ pub fn start() {
     let canvas = canvas.dyn_into::<HtmlCanvasElement>().unwrap();
     let renderer = Renderer::new(&canvas, 0., None);
     let mut grid = Grid::new(100, 50);
-    let (start, target) = grid.set_rand_start_n_end(&|| Math::random());
+    let mut rng = Rng::new((Math::random() * u32::MAX as f64) as u64);
+    let (start, target) = grid.set_rand_start_n_end(&mut rng);
     let graph = AStarBidirectional::new(AStarConfig {
         start,
         target,
         diagonal: false,
         multithreaded: false,
         bidirectional: false,
+        weighting: Weighting::Exact,
+        algorithm: Algorithm::AStar,
+        corner_cutting: CornerCutting::Always,
+        stencil: Stencil::Compass,
+        turns: None,
     });
     //start, target, false, false, false);
-    let app = App::new(canvas, grid, graph, renderer);
+    let app = App::new(canvas, grid, graph, renderer, rng);
     app.start();
 }
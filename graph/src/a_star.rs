@@ -1,18 +1,31 @@
-use crate::{is_odd, Cell, Cost, Direction, Grid, GridType, Node, Position};
+use crate::{
+    Algorithm, Cell, CornerCutting, Cost, CostType, Grid, GridType, Move, Moves, Node, Position,
+    Priority, Regions, Stencil, TurnCosts, Weighting,
+};
 use priority_queue::PriorityQueue;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
-use strum::IntoEnumIterator;
 
-#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 pub struct AStarConfig {
     pub start: Position,
     pub target: Position,
     pub diagonal: bool,
     pub multithreaded: bool,
     pub bidirectional: bool,
+    #[serde(default)]
+    pub weighting: Weighting,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+    #[serde(default)]
+    pub corner_cutting: CornerCutting,
+    #[serde(default)]
+    pub stencil: Stencil,
+    /// Searches over headings with `HeadingAStar` when set.
+    #[serde(default)]
+    pub turns: Option<TurnCosts>,
 }
 
 impl Default for AStarConfig {
@@ -23,31 +36,35 @@ impl Default for AStarConfig {
             target: Position::new(0, 0),
             multithreaded: false,
             bidirectional: false,
+            weighting: Weighting::Exact,
+            algorithm: Algorithm::AStar,
+            corner_cutting: CornerCutting::Always,
+            stencil: Stencil::Compass,
+            turns: None,
         }
     }
 }
 
 #[derive(Clone)]
-pub enum AStarData {
-    SingleThreaded(PriorityQueue<Node, Cost>, HashSet<Node>),
+pub enum AStarData<C: CostType = usize> {
+    SingleThreaded(PriorityQueue<Node, Priority<C>>, HashSet<Node>),
     Multithreaded(
-        Arc<Mutex<PriorityQueue<Node, Cost>>>,
+        Arc<Mutex<PriorityQueue<Node, Priority<C>>>>,
         Arc<Mutex<HashSet<Node>>>,
     ),
 }
 
-impl AStarData {
-    pub fn new(start: Position, target: Position, multithreaded: bool) -> Self {
+impl<C: CostType> AStarData<C> {
+    /// Opens with `start`, estimated `h_cost` away from where it searches to.
+    pub fn new(start: Position, h_cost: C, multithreaded: bool, algorithm: Algorithm) -> Self {
         let mut open = PriorityQueue::new();
         let closed = HashSet::new();
         let start_node = Node::new_from_pos(start);
-        open.push(
-            start_node,
-            Cost {
-                g_cost: 0,
-                h_cost: start.h_cost(&target),
-            },
-        );
+        let cost = Cost {
+            g_cost: C::ZERO,
+            h_cost,
+        };
+        open.push(start_node, algorithm.priority(cost, 0));
         if multithreaded {
             Self::Multithreaded(Arc::new(Mutex::new(open)), Arc::new(Mutex::new(closed)))
         } else {
@@ -105,18 +122,22 @@ impl AStarData {
             }
         };
     }
-    pub fn push_node_open(&mut self, node: Node, cost: Cost) {
+    pub fn push_node_open(&mut self, node: Node, priority: Priority<C>) {
         match self {
             Self::SingleThreaded(open, _) => {
-                open.push(node, cost);
+                open.push(node, priority);
             }
             Self::Multithreaded(open, _) => {
-                open.lock().unwrap().push(node, cost);
+                open.lock().unwrap().push(node, priority);
             }
         }
     }
-    pub fn push_open(&mut self, start: Position, h_cost: usize) {
-        self.push_node_open(Node::new_from_pos(start), Cost { g_cost: 0, h_cost });
+    pub fn push_open(&mut self, start: Position, h_cost: C, algorithm: Algorithm) {
+        let cost = Cost {
+            g_cost: C::ZERO,
+            h_cost,
+        };
+        self.push_node_open(Node::new_from_pos(start), algorithm.priority(cost, 0));
     }
     pub fn trace(&self, common_node: &Node, end_points: (Position, Position)) -> Vec<Position> {
         let mut path = Vec::new();
@@ -153,7 +174,7 @@ impl AStarData {
     pub fn modify_grid(&mut self, grid: &mut Grid, end_points: (Position, Position)) {
         let mut grid_set = |pos: Position, cell| {
             if pos != end_points.0 && pos != end_points.1 {
-                grid.set(pos.x, pos.y, cell);
+                grid.set_overlay(pos, cell);
             }
         };
         match self {
@@ -180,16 +201,23 @@ impl AStarData {
     }
     pub fn get_neighbour(
         grid_type: GridType,
-        current: (&Node, Cost),
-        dir: Direction,
-        target: &Position,
-    ) -> Result<(Node, Cost), String> {
+        current: (&Node, Cost<C>),
+        (step, corner_cutting): (Move, CornerCutting),
+        (target, (stencil, diagonal)): (&Position, (&Stencil, bool)),
+    ) -> Result<(Node, Cost<C>), String> {
         let neighbour_node = match grid_type {
-            GridType::Full(grid) => current.0.get_neighbour_from_grid(dir, grid),
-            GridType::Set(set) => current.0.get_neighbour_from_set(dir, set),
+            GridType::Full(grid) => current
+                .0
+                .get_neighbour_from_grid(step, grid, corner_cutting),
+            GridType::Set(set) => current.0.get_neighbour_from_set(step, set, corner_cutting),
+            GridType::Chunked(chunks) => {
+                current
+                    .0
+                    .get_neighbour_from_chunks(step, chunks, corner_cutting)
+            }
         }?;
-        let cost = current.1.g_cost + dir.g_cost();
-        let h_cost = neighbour_node.pos.h_cost(target);
+        let cost = current.1.g_cost.saturating_add(C::of_move(&step));
+        let h_cost = C::estimate(stencil, diagonal, neighbour_node.pos, *target);
         let neighbour_cost = Cost {
             g_cost: cost,
             h_cost,
@@ -197,21 +225,25 @@ impl AStarData {
         Ok((neighbour_node, neighbour_cost))
     }
     pub fn find_in_dir(
-        target: &Position,
-        current: (&Node, Cost),
-        a_star: (&mut PriorityQueue<Node, Cost>, &mut HashSet<Node>),
+        target: (&Position, (&Stencil, bool)),
+        current: (&Node, Priority<C>),
+        a_star: (&mut PriorityQueue<Node, Priority<C>>, &mut HashSet<Node>),
         grid_type: GridType,
-        dir: Direction,
+        step: (Move, CornerCutting),
     ) {
         let (current_node, current_cost) = current;
         let (open, closed) = a_star;
         if let Ok((mut neighbour, neighbour_cost)) =
-            Self::get_neighbour(grid_type, (&current_node, current_cost), dir, target)
+            Self::get_neighbour(grid_type, (&current_node, current_cost.cost), step, target)
         {
             if !closed.contains(&neighbour) {
-                let update_open = |(old_n, old_n_cost): (&mut Node, &mut Cost)| {
+                let algorithm = current_cost.algorithm;
+                let neighbour_cost = algorithm.priority(neighbour_cost, current_cost.depth + 1);
+                let update_open = |(old_n, old_n_cost): (&mut Node, &mut Priority<C>)| {
                     if old_n.pos == neighbour.pos {
-                        if neighbour_cost.g_cost < old_n_cost.g_cost {
+                        if algorithm.relaxes()
+                            && neighbour_cost.cost.g_cost < old_n_cost.cost.g_cost
+                        {
                             old_n.set_parent(current_node.clone());
                             *old_n_cost = neighbour_cost;
                         }
@@ -235,24 +267,28 @@ impl AStarData {
         }
     }
     pub fn find_in_dir_par(
-        target: &Position,
-        current: (&Node, Cost),
+        target: (&Position, (&Stencil, bool)),
+        current: (&Node, Priority<C>),
         a_star: (
-            Arc<Mutex<PriorityQueue<Node, Cost>>>,
+            Arc<Mutex<PriorityQueue<Node, Priority<C>>>>,
             Arc<Mutex<HashSet<Node>>>,
         ),
         grid_type: GridType,
-        dir: Direction,
+        step: (Move, CornerCutting),
     ) {
         let (current_node, current_cost) = current;
         let (open, closed) = a_star;
         if let Ok((mut neighbour, neighbour_cost)) =
-            Self::get_neighbour(grid_type, (&current_node, current_cost), dir, target)
+            Self::get_neighbour(grid_type, (&current_node, current_cost.cost), step, target)
         {
             if !closed.lock().unwrap().contains(&neighbour) {
-                let update_open = |(old_n, old_n_cost): (&mut Node, &mut Cost)| {
+                let algorithm = current_cost.algorithm;
+                let neighbour_cost = algorithm.priority(neighbour_cost, current_cost.depth + 1);
+                let update_open = |(old_n, old_n_cost): (&mut Node, &mut Priority<C>)| {
                     if old_n.pos == neighbour.pos {
-                        if neighbour_cost.g_cost < old_n_cost.g_cost {
+                        if algorithm.relaxes()
+                            && neighbour_cost.cost.g_cost < old_n_cost.cost.g_cost
+                        {
                             old_n.set_parent(current_node.clone());
                             *old_n_cost = neighbour_cost;
                         }
@@ -275,44 +311,48 @@ impl AStarData {
             }
         }
     }
-    pub fn find(&mut self, grid_type: GridType, target: &Position, diagonal: bool) {
+    pub fn find(
+        &mut self,
+        grid_type: GridType,
+        target: &Position,
+        (moves, corner_cutting): (Moves, CornerCutting),
+        estimate: (&Stencil, bool),
+    ) {
         match self {
             Self::SingleThreaded(open, closed) => {
                 let (current_node, current_cost) = open.pop().unwrap();
-                for (i, dir) in Direction::iter().enumerate() {
-                    if is_odd(i) || diagonal {
-                        Self::find_in_dir(
-                            target,
-                            (&current_node, current_cost),
-                            (open, closed),
-                            grid_type,
-                            dir,
-                        )
-                    }
+                for step in moves.iter() {
+                    Self::find_in_dir(
+                        (target, estimate),
+                        (&current_node, current_cost),
+                        (open, closed),
+                        grid_type,
+                        (*step, corner_cutting),
+                    )
                 }
                 closed.insert(current_node);
             }
             Self::Multithreaded(_, _) => {
-                self.find_par(grid_type, target, diagonal);
+                self.find_par(grid_type, target, (moves, corner_cutting), estimate);
             }
         }
     }
-    pub fn find_par(&self, grid_type: GridType, target: &Position, diagonal: bool) {
+    pub fn find_par(
+        &self,
+        grid_type: GridType,
+        target: &Position,
+        (moves, corner_cutting): (Moves, CornerCutting),
+        estimate: (&Stencil, bool),
+    ) {
         if let Self::Multithreaded(open, closed) = self {
             let (current_node, current_cost) = open.lock().unwrap().pop().unwrap();
-            let range = if diagonal { 0..8 } else { 0..4 };
-            range.into_par_iter().for_each(|i| {
-                let dir = if diagonal {
-                    Direction::from(i as u8)
-                } else {
-                    Direction::from((i * 2) as u8)
-                };
+            moves.as_slice().par_iter().for_each(|step| {
                 Self::find_in_dir_par(
-                    target,
+                    (target, estimate),
                     (&current_node, current_cost),
                     (open.clone(), closed.clone()),
                     grid_type,
-                    dir,
+                    (*step, corner_cutting),
                 );
             });
             closed.lock().unwrap().insert(current_node);
@@ -338,25 +378,43 @@ impl AStarData {
     }
 }
 
+/// Costs add up in `usize` unless another `CostType` is picked with
+/// `with_cost_type`.
 #[derive(Clone)]
-pub struct AStarBidirectional {
+pub struct AStarBidirectional<C: CostType = usize> {
     start: Position,
-    start_data: AStarData,
+    start_data: AStarData<C>,
     target: Position,
-    target_data: Option<AStarData>,
+    target_data: Option<AStarData<C>>,
     common_node: Option<Node>,
+    algorithm: Algorithm,
     pub diagonal: bool,
+    pub corner_cutting: CornerCutting,
+    pub stencil: Stencil,
 }
 
 impl AStarBidirectional {
     pub fn new(config: AStarConfig) -> Self {
+        Self::with_cost_type(config)
+    }
+}
+
+impl<C: CostType> AStarBidirectional<C> {
+    pub fn with_cost_type(config: AStarConfig) -> Self {
+        let h_cost = C::estimate(
+            &config.stencil,
+            config.diagonal,
+            config.start,
+            config.target,
+        );
         let (start_data, target_data) = (
-            AStarData::new(config.start, config.target, config.multithreaded),
+            AStarData::new(config.start, h_cost, config.multithreaded, config.algorithm),
             if config.bidirectional {
                 Some(AStarData::new(
                     config.target,
-                    config.start,
+                    h_cost,
                     config.multithreaded,
+                    config.algorithm,
                 ))
             } else {
                 None
@@ -368,7 +426,10 @@ impl AStarBidirectional {
             target: config.target,
             target_data,
             common_node: None,
+            algorithm: config.algorithm,
             diagonal: config.diagonal,
+            corner_cutting: config.corner_cutting,
+            stencil: config.stencil,
         }
     }
     pub fn multithreaded(&self) -> bool {
@@ -389,13 +450,21 @@ impl AStarBidirectional {
         if bidirectional {
             self.target_data = Some(AStarData::new(
                 self.target,
-                self.start,
+                self.h_cost(),
                 self.multithreaded(),
+                self.algorithm,
             ));
         } else {
             self.target_data = None;
         }
     }
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm;
+        self.clear();
+    }
     pub fn solved(&mut self) -> bool {
         let mut solved = false;
         if let Some(node) = self
@@ -413,13 +482,18 @@ impl AStarBidirectional {
         }
         solved
     }
+    /// The estimate between the end points, either way round.
+    fn h_cost(&self) -> C {
+        C::estimate(&self.stencil, self.diagonal, self.start, self.target)
+    }
     pub fn clear(&mut self) {
-        let h_cost = self.start.h_cost(&self.target);
+        let h_cost = self.h_cost();
         self.start_data.clear();
-        self.start_data.push_open(self.start, h_cost);
+        self.start_data
+            .push_open(self.start, h_cost, self.algorithm);
         if let Some(ref mut t_d) = self.target_data {
             t_d.clear();
-            t_d.push_open(self.target, h_cost);
+            t_d.push_open(self.target, h_cost, self.algorithm);
         }
     }
     pub fn start(&self) -> Position {
@@ -473,6 +547,13 @@ impl AStarBidirectional {
             return;
         }
         let (multithreaded, bidirectional) = (self.multithreaded(), self.bidirectional());
+        let moves = self.stencil.moves(self.diagonal);
+        let (moves, reversed) = (
+            (moves, self.corner_cutting),
+            (moves.reversed(), self.corner_cutting),
+        );
+        let stencil = self.stencil;
+        let estimate = (&stencil, self.diagonal);
         if multithreaded && bidirectional {
             let (start_data, target_data) = (
                 self.start_data.clone(),
@@ -480,15 +561,16 @@ impl AStarBidirectional {
             );
             (0..2).into_par_iter().for_each(move |i| {
                 if i == 0 {
-                    start_data.find_par(grid_type, &self.target, self.diagonal);
+                    start_data.find_par(grid_type, &self.target, moves, estimate);
                 } else {
-                    target_data.find_par(grid_type, &self.start, self.diagonal);
+                    target_data.find_par(grid_type, &self.start, reversed, estimate);
                 }
             });
         } else {
-            self.start_data.find(grid_type, &self.target, self.diagonal);
+            self.start_data
+                .find(grid_type, &self.target, moves, estimate);
             if let Some(ref mut t_d) = self.target_data {
-                t_d.find(grid_type, &self.start, self.diagonal);
+                t_d.find(grid_type, &self.start, reversed, estimate);
             }
         }
     }
@@ -508,6 +590,15 @@ impl AStarBidirectional {
         }
         self.trace()
     }
+    /// Like `AStarTrait::solve_within`, returns no path without searching
+    /// when the end points are in different regions.
+    pub fn solve_within(&mut self, grid: GridType, regions: &Regions) -> Vec<Position> {
+        if regions.connected(self.start, self.target) {
+            self.solve(grid)
+        } else {
+            Vec::new()
+        }
+    }
     pub fn trace(&self) -> Vec<Position> {
         let mut path = Vec::new();
         let end_points = self.end_points();
@@ -0,0 +1,75 @@
+use crate::{Cost, CostType};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use strum_macros::{EnumIter, EnumString};
+
+/// Which node `AStarBidirectional` expands next. Only A* and Dijkstra revisit
+/// a node when a cheaper way to it turns up, so only they return shortest
+/// paths; the others keep the first parent they found.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, EnumIter, EnumString, Serialize, Deserialize,
+)]
+pub enum Algorithm {
+    /// Lowest cost so far plus the estimate to the target.
+    #[default]
+    AStar,
+    /// Lowest cost so far.
+    Dijkstra,
+    /// Lowest estimate to the target.
+    Greedy,
+    /// Fewest steps from the start.
+    BreadthFirst,
+    /// Most steps from the start.
+    DepthFirst,
+}
+
+impl Algorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::AStar => "A*",
+            Algorithm::Dijkstra => "Dijkstra",
+            Algorithm::Greedy => "Greedy best-first",
+            Algorithm::BreadthFirst => "Breadth-first",
+            Algorithm::DepthFirst => "Depth-first",
+        }
+    }
+    /// Whether a cheaper path to a node already in the open list replaces
+    /// the one it was found by.
+    pub fn relaxes(&self) -> bool {
+        matches!(self, Algorithm::AStar | Algorithm::Dijkstra)
+    }
+    pub fn priority<C: CostType>(&self, cost: Cost<C>, depth: usize) -> Priority<C> {
+        Priority {
+            algorithm: *self,
+            cost,
+            depth,
+        }
+    }
+}
+
+/// A node's place in the open list: its cost along with how many steps it is
+/// from the start, ordered the way the algorithm picks nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Priority<C = usize> {
+    pub algorithm: Algorithm,
+    pub cost: Cost<C>,
+    pub depth: usize,
+}
+
+impl<C: CostType> Ord for Priority<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.algorithm {
+            Algorithm::AStar => self.cost.cmp(&other.cost),
+            Algorithm::Dijkstra => other.cost.g_cost.cmp(&self.cost.g_cost),
+            Algorithm::Greedy => other.cost.h_cost.cmp(&self.cost.h_cost),
+            Algorithm::BreadthFirst => other.depth.cmp(&self.depth),
+            Algorithm::DepthFirst => self.depth.cmp(&other.depth),
+        }
+    }
+}
+
+impl<C: CostType> PartialOrd for Priority<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{AStarConfig, CornerCutting, GridType, Moves, Node, Position, Stencil};
+use priority_queue::PriorityQueue;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// How far the search may trust the heuristic over the cost found so far.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Weighting {
+    /// Plain A*.
+    #[default]
+    Exact,
+    /// Weighted A*: the heuristic is inflated by `epsilon`, so fewer cells are
+    /// expanded and the path costs at most `epsilon` times the optimum.
+    Weighted(f64),
+    /// ARA*: a weighted search starting at `epsilon` that is repeated with
+    /// `epsilon` lowered by `step`, reusing the previous work, until the path
+    /// is optimal or `millis` have passed.
+    Anytime {
+        epsilon: f64,
+        step: f64,
+        millis: u64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    /// The cells between start and target, both excluded.
+    pub path: Vec<Position>,
+    pub cost: usize,
+    /// The path costs at most this many times the optimum.
+    pub bound: f64,
+}
+
+/// Anytime Repairing A* over `Stencil::heuristic`, which covers weighted and
+/// plain A* as a search that stops after its first solution.
+#[derive(Clone)]
+pub struct AnytimeAStar {
+    start: Position,
+    target: Position,
+    diagonal: bool,
+    corner_cutting: CornerCutting,
+    stencil: Stencil,
+    moves: Moves,
+    epsilon: f64,
+    step: f64,
+    time_limit: Option<Duration>,
+    g_costs: HashMap<Position, usize>,
+    parents: HashMap<Position, Position>,
+    open: PriorityQueue<Position, Reverse<u64>>,
+    closed: HashSet<Position>,
+    incons: HashSet<Position>,
+    solution: Option<Solution>,
+    done: bool,
+}
+
+impl AnytimeAStar {
+    pub fn new(config: AStarConfig) -> Self {
+        let (epsilon, step, time_limit) = match config.weighting {
+            Weighting::Exact => (1., 0., None),
+            Weighting::Weighted(epsilon) => (epsilon, 0., None),
+            Weighting::Anytime {
+                epsilon,
+                step,
+                millis,
+            } => (epsilon, step, Some(Duration::from_millis(millis))),
+        };
+        let mut search = Self {
+            start: config.start,
+            target: config.target,
+            diagonal: config.diagonal,
+            corner_cutting: config.corner_cutting,
+            stencil: config.stencil,
+            moves: config.stencil.moves(config.diagonal),
+            epsilon: epsilon.max(1.),
+            step,
+            time_limit,
+            g_costs: HashMap::new(),
+            parents: HashMap::new(),
+            open: PriorityQueue::new(),
+            closed: HashSet::new(),
+            incons: HashSet::new(),
+            solution: None,
+            done: false,
+        };
+        search.g_costs.insert(search.start, 0);
+        search
+            .open
+            .push(search.start, Reverse(search.f_key(search.start)));
+        search
+    }
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+    /// The best solution found so far.
+    pub fn solution(&self) -> Option<&Solution> {
+        self.solution.as_ref()
+    }
+    /// Runs one search at the current `epsilon` and lowers it for the next
+    /// call. Returns `false` once the solution can't improve any further.
+    pub fn improve(&mut self, grid: GridType) -> bool {
+        if self.done {
+            return false;
+        }
+        if self.solution.is_some() {
+            self.epsilon = (self.epsilon - self.step).max(1.);
+            let incons: Vec<Position> = self.incons.drain().collect();
+            let mut open: Vec<Position> = self.open.iter().map(|(pos, _)| *pos).collect();
+            open.extend(incons);
+            self.open.clear();
+            for pos in open {
+                let key = self.f_key(pos);
+                self.open.push(pos, Reverse(key));
+            }
+            self.closed.clear();
+        }
+        self.improve_path(grid);
+        if !self.g_costs.contains_key(&self.target) {
+            self.done = true;
+            return false;
+        }
+        let (path, cost) = self.trace();
+        let lowest = self
+            .open
+            .iter()
+            .map(|(pos, _)| *pos)
+            .chain(self.incons.iter().copied())
+            .map(|pos| self.g_costs[&pos].saturating_add(self.heuristic(pos)))
+            .min();
+        let bound = match lowest {
+            Some(lowest) if lowest > 0 => self.epsilon.min(cost as f64 / lowest as f64).max(1.),
+            _ => 1.,
+        };
+        self.solution = Some(Solution { path, cost, bound });
+        self.done = bound <= 1. || self.step <= 0.;
+        !self.done
+    }
+    /// Improves the solution until it is optimal, can't get better or the
+    /// time limit of `Weighting::Anytime` runs out.
+    pub fn solve(&mut self, grid: GridType) -> Option<Solution> {
+        let then = Instant::now();
+        while self.improve(grid) {
+            if let Some(limit) = self.time_limit {
+                if then.elapsed() >= limit {
+                    break;
+                }
+            }
+        }
+        self.solution.clone()
+    }
+    pub fn get_open_and_closed_list(&self) -> (Vec<Position>, Vec<Position>) {
+        (
+            self.open.iter().map(|(pos, _)| *pos).collect(),
+            self.closed.iter().copied().collect(),
+        )
+    }
+    fn improve_path(&mut self, grid: GridType) {
+        let target_key = |search: &Self| match search.g_costs.get(&search.target) {
+            Some(_) => search.f_key(search.target),
+            None => u64::MAX,
+        };
+        while let Some((_, Reverse(key))) = self.open.peek() {
+            if target_key(self) <= *key {
+                break;
+            }
+            let (pos, _) = self.open.pop().unwrap();
+            self.closed.insert(pos);
+            let g_cost = self.g_costs[&pos];
+            let current = Node::new_from_pos(pos);
+            for step in self.moves.iter() {
+                let neighbour = match grid {
+                    GridType::Full(grid) => {
+                        current.get_neighbour_from_grid(*step, grid, self.corner_cutting)
+                    }
+                    GridType::Set(set) => {
+                        current.get_neighbour_from_set(*step, set, self.corner_cutting)
+                    }
+                    GridType::Chunked(chunks) => {
+                        current.get_neighbour_from_chunks(*step, chunks, self.corner_cutting)
+                    }
+                };
+                if let Ok(neighbour) = neighbour {
+                    let next = neighbour.pos;
+                    let n_cost = g_cost.saturating_add(step.cost);
+                    let cheaper = match self.g_costs.get(&next) {
+                        Some(old) => n_cost < *old,
+                        None => true,
+                    };
+                    if cheaper {
+                        self.g_costs.insert(next, n_cost);
+                        self.parents.insert(next, pos);
+                        if self.closed.contains(&next) {
+                            self.incons.insert(next);
+                        } else {
+                            let key = self.f_key(next);
+                            self.open.push(next, Reverse(key));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    fn heuristic(&self, pos: Position) -> usize {
+        self.stencil.heuristic(self.diagonal, pos, self.target)
+    }
+    fn f_key(&self, pos: Position) -> u64 {
+        let h_cost = self.heuristic(pos) as f64;
+        // scaled so fractional weights still order correctly
+        ((self.g_costs[&pos] as f64 + self.epsilon * h_cost) * 1000.) as u64
+    }
+    /// Follows the parents back from the target. Cells improved after their
+    /// children were expanded leave the path cheaper than `g(target)`, so the
+    /// cost is summed along the way.
+    fn trace(&self) -> (Vec<Position>, usize) {
+        let mut path = Vec::new();
+        let mut cost = 0;
+        let mut current = self.target;
+        while let Some(parent) = self.parents.get(&current) {
+            if let Some(step) = self.moves.between(*parent, current) {
+                cost = usize::saturating_add(cost, step.cost);
+            }
+            if *parent != self.start {
+                path.push(*parent);
+            }
+            current = *parent;
+        }
+        (path, cost)
+    }
+}
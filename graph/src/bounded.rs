@@ -0,0 +1,493 @@
+use crate::{AStarConfig, CornerCutting, GridType, Moves, Node, Position, Stencil};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+
+const UNREACHABLE: usize = usize::MAX;
+
+fn successors(
+    grid: GridType,
+    pos: Position,
+    (moves, corner_cutting): (&Moves, CornerCutting),
+) -> Vec<(Position, usize)> {
+    let current = Node::new_from_pos(pos);
+    moves
+        .iter()
+        .filter_map(|step| {
+            let neighbour = match grid {
+                GridType::Full(grid) => {
+                    current.get_neighbour_from_grid(*step, grid, corner_cutting)
+                }
+                GridType::Set(set) => current.get_neighbour_from_set(*step, set, corner_cutting),
+                GridType::Chunked(chunks) => {
+                    current.get_neighbour_from_chunks(*step, chunks, corner_cutting)
+                }
+            };
+            neighbour.ok().map(|neighbour| (neighbour.pos, step.cost))
+        })
+        .collect()
+}
+
+struct Frame {
+    pos: Position,
+    g_cost: usize,
+    successors: Vec<(Position, usize)>,
+    next: usize,
+}
+
+/// Iterative deepening A*: repeated depth first searches, each bounded by the
+/// lowest cost that overran the last one. Only the current path is needed,
+/// plus a table of the cells reached in the current iteration so they aren't
+/// searched twice.
+///
+/// `limit` caps the cells held by the two together. Once the path alone is
+/// that long the search won't go deeper, so paths through more cells than
+/// `limit` aren't found. The table simply stops growing when it's full, and
+/// with it small, maps with many equally good detours take far longer than A*.
+#[derive(Clone)]
+pub struct IdaStar {
+    start: Position,
+    target: Position,
+    diagonal: bool,
+    corner_cutting: CornerCutting,
+    stencil: Stencil,
+    moves: Moves,
+    limit: usize,
+    path: Vec<Position>,
+    seen: HashMap<Position, usize>,
+    iterations: usize,
+}
+
+impl IdaStar {
+    pub fn new(config: AStarConfig, limit: usize) -> Self {
+        Self {
+            start: config.start,
+            target: config.target,
+            diagonal: config.diagonal,
+            corner_cutting: config.corner_cutting,
+            stencil: config.stencil,
+            moves: config.stencil.moves(config.diagonal),
+            limit,
+            path: Vec::new(),
+            seen: HashMap::new(),
+            iterations: 0,
+        }
+    }
+    /// Number of depth first searches run by the last `solve`.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+    /// The optimal path within the cap, without start and target, from the
+    /// target back to the start like the other solvers.
+    pub fn solve(&mut self, grid: GridType) -> Vec<Position> {
+        let mut threshold = self
+            .stencil
+            .heuristic(self.diagonal, self.start, self.target);
+        self.iterations = 0;
+        loop {
+            self.iterations += 1;
+            match self.search(grid, threshold) {
+                Ok(()) => break,
+                Err(Some(next)) => threshold = next,
+                Err(None) => {
+                    self.path.clear();
+                    break;
+                }
+            }
+        }
+        self.seen.clear();
+        self.path
+            .iter()
+            .rev()
+            .copied()
+            .filter(|pos| *pos != self.start && *pos != self.target)
+            .collect()
+    }
+    /// The path being searched is all there is to show, so it's the open list
+    /// and nothing is closed.
+    pub fn get_open_and_closed_list(&self) -> (Vec<Position>, Vec<Position>) {
+        (self.path.clone(), Vec::new())
+    }
+    /// Leaves the path in `self.path` if the target is within `threshold`,
+    /// otherwise returns the lowest cost above it, if anything was cut off.
+    fn search(&mut self, grid: GridType, threshold: usize) -> Result<(), Option<usize>> {
+        self.seen.clear();
+        self.path.clear();
+        if self.start == self.target {
+            self.path.push(self.start);
+            return Ok(());
+        }
+        let mut lowest = None;
+        let mut stack = vec![Frame {
+            pos: self.start,
+            g_cost: 0,
+            successors: successors(grid, self.start, (&self.moves, self.corner_cutting)),
+            next: 0,
+        }];
+        self.seen.insert(self.start, 0);
+        while let Some(frame) = stack.last_mut() {
+            let (pos, g_cost) = match frame.successors.get(frame.next) {
+                Some((pos, cost)) => (*pos, frame.g_cost.saturating_add(*cost)),
+                None => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            frame.next += 1;
+            let f_cost =
+                g_cost.saturating_add(self.stencil.heuristic(self.diagonal, pos, self.target));
+            if f_cost > threshold {
+                lowest = Some(lowest.map_or(f_cost, |lowest: usize| lowest.min(f_cost)));
+                continue;
+            }
+            if matches!(self.seen.get(&pos), Some(seen) if *seen <= g_cost)
+                || stack.iter().any(|frame| frame.pos == pos)
+                || stack.len() >= self.limit
+            {
+                continue;
+            }
+            if self.seen.contains_key(&pos) || self.seen.len() + stack.len() < self.limit {
+                self.seen.insert(pos, g_cost);
+            }
+            if pos == self.target {
+                self.path = stack.iter().map(|frame| frame.pos).collect();
+                self.path.push(pos);
+                return Ok(());
+            }
+            stack.push(Frame {
+                pos,
+                g_cost,
+                successors: successors(grid, pos, (&self.moves, self.corner_cutting)),
+                next: 0,
+            });
+        }
+        Err(lowest)
+    }
+}
+
+type Key = (usize, Reverse<usize>, usize);
+
+#[derive(Clone)]
+struct Successor {
+    pos: Position,
+    g_cost: usize,
+    f_cost: usize,
+    child: Option<usize>,
+}
+
+#[derive(Clone)]
+struct SmaNode {
+    pos: Position,
+    g_cost: usize,
+    f_cost: usize,
+    depth: usize,
+    parent: Option<usize>,
+    successors: Option<Vec<Successor>>,
+    children: usize,
+    queued: Option<Key>,
+    leaf: Option<Key>,
+}
+
+/// Simplified memory-bounded A*. It searches like A* until `limit` nodes are
+/// held, then forgets the worst leaf to make room, keeping its cost in the
+/// parent so the branch is only grown again once it looks best.
+///
+/// The path is optimal as long as it fits in `limit` nodes, at the price of
+/// regenerating forgotten branches when memory is tight.
+#[derive(Clone)]
+pub struct SmaStar {
+    start: Position,
+    target: Position,
+    diagonal: bool,
+    corner_cutting: CornerCutting,
+    stencil: Stencil,
+    moves: Moves,
+    limit: usize,
+    nodes: Vec<Option<SmaNode>>,
+    free: Vec<usize>,
+    at: HashMap<Position, Vec<usize>>,
+    open: BTreeSet<Key>,
+    leaves: BTreeSet<Key>,
+    goal: Option<usize>,
+}
+
+impl SmaStar {
+    pub fn new(config: AStarConfig, limit: usize) -> Self {
+        Self {
+            start: config.start,
+            target: config.target,
+            diagonal: config.diagonal,
+            corner_cutting: config.corner_cutting,
+            stencil: config.stencil,
+            moves: config.stencil.moves(config.diagonal),
+            limit: limit.max(1),
+            nodes: Vec::new(),
+            free: Vec::new(),
+            at: HashMap::new(),
+            open: BTreeSet::new(),
+            leaves: BTreeSet::new(),
+            goal: None,
+        }
+    }
+    /// Number of nodes held right now.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The optimal path within the cap, without start and target, from the
+    /// target back to the start like the other solvers.
+    pub fn solve(&mut self, grid: GridType) -> Vec<Position> {
+        self.reset();
+        let root = self.insert(SmaNode {
+            pos: self.start,
+            g_cost: 0,
+            f_cost: self
+                .stencil
+                .heuristic(self.diagonal, self.start, self.target),
+            depth: 0,
+            parent: None,
+            successors: None,
+            children: 0,
+            queued: None,
+            leaf: None,
+        });
+        self.refresh(root);
+        while let Some(&(_, _, id)) = self.open.iter().next() {
+            if self.node(id).successors.is_none() {
+                if self.node(id).pos == self.target {
+                    self.goal = Some(id);
+                    break;
+                }
+                self.expand(grid, id);
+                continue;
+            }
+            let index = self
+                .node(id)
+                .successors
+                .as_ref()
+                .unwrap()
+                .iter()
+                .enumerate()
+                .filter(|(_, each)| each.child.is_none() && each.f_cost < UNREACHABLE)
+                .min_by_key(|(_, each)| each.f_cost)
+                .map(|(i, _)| i)
+                .unwrap();
+            self.generate(id, index);
+        }
+        self.trace()
+    }
+    pub fn get_open_and_closed_list(&self) -> (Vec<Position>, Vec<Position>) {
+        let open = self.open.iter().map(|(_, _, id)| self.node(*id).pos);
+        let closed = self
+            .nodes
+            .iter()
+            .flatten()
+            .filter(|node| node.successors.is_some())
+            .map(|node| node.pos);
+        (open.collect(), closed.collect())
+    }
+    fn reset(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.at.clear();
+        self.open.clear();
+        self.leaves.clear();
+        self.goal = None;
+    }
+    fn node(&self, id: usize) -> &SmaNode {
+        self.nodes[id].as_ref().unwrap()
+    }
+    fn node_mut(&mut self, id: usize) -> &mut SmaNode {
+        self.nodes[id].as_mut().unwrap()
+    }
+    fn insert(&mut self, node: SmaNode) -> usize {
+        let pos = node.pos;
+        let id = match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = Some(node);
+                id
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.at.entry(pos).or_default().push(id);
+        id
+    }
+    /// Lists the successors of a node without allocating any of them.
+    fn expand(&mut self, grid: GridType, id: usize) {
+        let (pos, g_cost, f_cost, depth) = {
+            let node = self.node(id);
+            (node.pos, node.g_cost, node.f_cost, node.depth)
+        };
+        let successors = successors(grid, pos, (&self.moves, self.corner_cutting))
+            .into_iter()
+            .map(|(next, cost)| {
+                let g_cost = g_cost.saturating_add(cost);
+                // a node at the last level can't have children, so only the
+                // target is worth keeping there
+                let too_deep =
+                    depth + 1 >= self.limit || (depth + 2 >= self.limit && next != self.target);
+                let f_cost = if too_deep {
+                    UNREACHABLE
+                } else {
+                    f_cost.max(g_cost.saturating_add(self.stencil.heuristic(
+                        self.diagonal,
+                        next,
+                        self.target,
+                    )))
+                };
+                Successor {
+                    pos: next,
+                    g_cost,
+                    f_cost,
+                    child: None,
+                }
+            })
+            .collect();
+        self.node_mut(id).successors = Some(successors);
+        self.back_up(id);
+    }
+    fn generate(&mut self, id: usize, index: usize) {
+        let successor = self.node(id).successors.as_ref().unwrap()[index].clone();
+        let dominated = matches!(self.at.get(&successor.pos), Some(ids)
+            if ids.iter().any(|other| self.node(*other).g_cost <= successor.g_cost));
+        if dominated || (self.len() >= self.limit && !self.forget(id)) {
+            self.node_mut(id).successors.as_mut().unwrap()[index].f_cost = UNREACHABLE;
+            self.back_up(id);
+            return;
+        }
+        let depth = self.node(id).depth + 1;
+        let child = self.insert(SmaNode {
+            pos: successor.pos,
+            g_cost: successor.g_cost,
+            f_cost: successor.f_cost,
+            depth,
+            parent: Some(id),
+            successors: None,
+            children: 0,
+            queued: None,
+            leaf: None,
+        });
+        let node = self.node_mut(id);
+        node.successors.as_mut().unwrap()[index].child = Some(child);
+        node.children += 1;
+        self.refresh(child);
+        self.refresh(id);
+    }
+    /// Drops the shallowest of the most expensive leaves other than `keep`,
+    /// remembering its cost in its parent.
+    fn forget(&mut self, keep: usize) -> bool {
+        let leaf = match self
+            .leaves
+            .iter()
+            .rev()
+            .map(|(_, _, id)| *id)
+            .find(|id| *id != keep)
+        {
+            Some(leaf) => leaf,
+            None => return false,
+        };
+        let node = self.nodes[leaf].take().unwrap();
+        if let Some(key) = node.queued {
+            self.open.remove(&key);
+        }
+        if let Some(key) = node.leaf {
+            self.leaves.remove(&key);
+        }
+        if let Some(ids) = self.at.get_mut(&node.pos) {
+            ids.retain(|id| *id != leaf);
+            if ids.is_empty() {
+                self.at.remove(&node.pos);
+            }
+        }
+        self.free.push(leaf);
+        let parent = node.parent.unwrap();
+        let parent_node = self.node_mut(parent);
+        for each in parent_node.successors.as_mut().unwrap().iter_mut() {
+            if each.child == Some(leaf) {
+                each.child = None;
+                each.f_cost = node.f_cost;
+            }
+        }
+        parent_node.children -= 1;
+        self.refresh(parent);
+        true
+    }
+    /// Raises a node's cost to the cheapest of its successors and passes the
+    /// change on to its ancestors.
+    fn back_up(&mut self, id: usize) {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            let node = self.node(id);
+            let f_cost = node
+                .successors
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|each| match each.child {
+                    Some(child) => self.node(child).f_cost,
+                    None => each.f_cost,
+                })
+                .min()
+                .unwrap_or(UNREACHABLE);
+            let changed = f_cost != node.f_cost;
+            current = node.parent;
+            self.node_mut(id).f_cost = f_cost;
+            self.refresh(id);
+            if !changed {
+                break;
+            }
+        }
+    }
+    /// Files a node under its current costs in the open list and among the
+    /// leaves that may be forgotten.
+    fn refresh(&mut self, id: usize) {
+        let node = self.node(id);
+        let open = match &node.successors {
+            None => Some(node.f_cost),
+            Some(successors) => successors
+                .iter()
+                .filter(|each| each.child.is_none())
+                .map(|each| each.f_cost)
+                .min(),
+        }
+        .filter(|f_cost| *f_cost < UNREACHABLE)
+        .map(|f_cost| (f_cost, Reverse(node.depth), id));
+        let leaf = if node.children == 0 && node.parent.is_some() {
+            Some((node.f_cost, Reverse(node.depth), id))
+        } else {
+            None
+        };
+        let (old_open, old_leaf) = (node.queued, node.leaf);
+        if let Some(key) = old_open {
+            self.open.remove(&key);
+        }
+        if let Some(key) = old_leaf {
+            self.leaves.remove(&key);
+        }
+        if let Some(key) = open {
+            self.open.insert(key);
+        }
+        if let Some(key) = leaf {
+            self.leaves.insert(key);
+        }
+        let node = self.node_mut(id);
+        node.queued = open;
+        node.leaf = leaf;
+    }
+    fn trace(&self) -> Vec<Position> {
+        let mut path = Vec::new();
+        let mut current = self.goal;
+        while let Some(id) = current {
+            let node = self.node(id);
+            if node.pos != self.start && node.pos != self.target {
+                path.push(node.pos);
+            }
+            current = node.parent;
+        }
+        path
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{Cell, Grid, Position};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Width and height of a chunk in cells.
+pub const CHUNK_SIZE: usize = 32;
+
+/// Signed coordinates of a chunk, so maps can grow in every direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChunkCoord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl ChunkCoord {
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+    /// The chunk holding the world cell at `x`, `y` and the cell's index in
+    /// it. Fails for cells so far out that their chunk has no coordinates.
+    pub fn of(x: i64, y: i64) -> Result<(Self, usize), String> {
+        let size = CHUNK_SIZE as i64;
+        let chunk = |v: i64| {
+            i32::try_from(v.div_euclid(size))
+                .map_err(|_| format!("The cell {}, {} is too far out for a chunk!", x, y))
+        };
+        let coord = Self::new(chunk(x)?, chunk(y)?);
+        let index = y.rem_euclid(size) as usize * CHUNK_SIZE + x.rem_euclid(size) as usize;
+        Ok((coord, index))
+    }
+    /// World coordinates of the chunk's north west corner.
+    pub fn origin(&self) -> (i64, i64) {
+        (
+            self.x as i64 * CHUNK_SIZE as i64,
+            self.y as i64 * CHUNK_SIZE as i64,
+        )
+    }
+    fn file_name(&self) -> String {
+        format!("{}_{}.chunk", self.x, self.y)
+    }
+    fn from_file_name(name: &str) -> Option<Self> {
+        let mut parts = name.strip_suffix(".chunk")?.splitn(2, '_');
+        let x = parts.next()?.parse().ok()?;
+        let y = parts.next()?.parse().ok()?;
+        Some(Self::new(x, y))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    blocked: Vec<bool>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Default for Chunk {
+    fn default() -> Self {
+        Self {
+            blocked: vec![false; CHUNK_SIZE * CHUNK_SIZE],
+            dirty: false,
+        }
+    }
+}
+
+/// A map split into square chunks keyed by signed coordinates. Only chunks
+/// with walls in them take up memory, and a map opened from a directory only
+/// reads a chunk from disk the first time one of its cells is looked at.
+///
+/// The solvers see the bounding box of every known chunk as a regular grid
+/// whose top left is `origin()`, see `to_position` and `to_world`. Cells in
+/// that box without a chunk are open ground. Only chunks are written to disk,
+/// so an area grown with `include` has to be included again after `open`.
+///
+/// A chunk file that can't be read counts as walls throughout, so a search
+/// goes around it instead of failing, and the error is kept for `errors`.
+///
+/// The solvers allocate state for the whole bounding box, so chunks that
+/// would stretch it past `MAX_CELLS` cells are rejected.
+#[derive(Debug, Default)]
+pub struct ChunkedGrid {
+    dir: Option<PathBuf>,
+    chunks: RwLock<HashMap<ChunkCoord, Chunk>>,
+    stored: HashSet<ChunkCoord>,
+    failed: RwLock<HashMap<ChunkCoord, String>>,
+    bounds: Option<(ChunkCoord, ChunkCoord)>,
+}
+
+impl ChunkedGrid {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Backs the grid with a directory of chunk files, creating it if needed.
+    /// Nothing but the file names is read until a chunk is used.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Couldn't create {}: {}", dir.display(), e))?;
+        let entries =
+            fs::read_dir(&dir).map_err(|e| format!("Couldn't read {}: {}", dir.display(), e))?;
+        let mut grid = Self::new();
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Couldn't read {}: {}", dir.display(), e))?;
+            if let Some(coord) = entry
+                .file_name()
+                .to_str()
+                .and_then(ChunkCoord::from_file_name)
+            {
+                grid.extend(coord)?;
+                grid.stored.insert(coord);
+            }
+        }
+        grid.dir = Some(dir);
+        Ok(grid)
+    }
+    /// Whether a world cell is a wall, which every cell of a chunk that
+    /// couldn't be read is, as are cells too far out to have a chunk.
+    pub fn is_blocked(&self, x: i64, y: i64) -> bool {
+        let (coord, index) = match ChunkCoord::of(x, y) {
+            Ok(chunk) => chunk,
+            Err(_) => return true,
+        };
+        if self.failed.read().unwrap().contains_key(&coord) {
+            return true;
+        }
+        if self.load(coord).is_err() {
+            return true;
+        }
+        matches!(self.chunks.read().unwrap().get(&coord), Some(chunk) if chunk.blocked[index])
+    }
+    /// Fails if the chunk of the cell is on disk but can't be read, or would
+    /// grow the grid too large, leaving the grid as it was.
+    pub fn set_blocked(&mut self, x: i64, y: i64, blocked: bool) -> Result<(), String> {
+        let (coord, index) = ChunkCoord::of(x, y)?;
+        self.load(coord)?;
+        self.write(coord, index, blocked)
+    }
+    /// Why each chunk that couldn't be read was treated as walls.
+    pub fn errors(&self) -> Vec<String> {
+        self.failed.read().unwrap().values().cloned().collect()
+    }
+    fn write(&mut self, coord: ChunkCoord, index: usize, blocked: bool) -> Result<(), String> {
+        if !blocked && !self.chunks.get_mut().unwrap().contains_key(&coord) {
+            return Ok(());
+        }
+        self.extend(coord)?;
+        let chunk = self.chunks.get_mut().unwrap().entry(coord).or_default();
+        chunk.blocked[index] = blocked;
+        chunk.dirty = true;
+        Ok(())
+    }
+    /// Reads a chunk from disk unless it is already in memory or was never
+    /// saved. A chunk that fails stays walls until a later `load` succeeds.
+    pub fn load(&self, coord: ChunkCoord) -> Result<(), String> {
+        if !self.stored.contains(&coord) || self.chunks.read().unwrap().contains_key(&coord) {
+            return Ok(());
+        }
+        match self.read(coord) {
+            Ok(chunk) => {
+                self.failed.write().unwrap().remove(&coord);
+                self.chunks.write().unwrap().entry(coord).or_insert(chunk);
+                Ok(())
+            }
+            Err(e) => {
+                self.failed.write().unwrap().insert(coord, e.clone());
+                Err(e)
+            }
+        }
+    }
+    fn read(&self, coord: ChunkCoord) -> Result<Chunk, String> {
+        let path = self
+            .path(coord)
+            .ok_or_else(|| format!("Chunk {:?} has no directory to be read from!", coord))?;
+        let bytes =
+            fs::read(&path).map_err(|e| format!("Couldn't read {}: {}", path.display(), e))?;
+        let chunk: Chunk = bincode::deserialize(&bytes)
+            .map_err(|e| format!("Invalid chunk {}: {}", path.display(), e))?;
+        if chunk.blocked.len() != CHUNK_SIZE * CHUNK_SIZE {
+            return Err(format!(
+                "Chunk {} has {} cells instead of {}!",
+                path.display(),
+                chunk.blocked.len(),
+                CHUNK_SIZE * CHUNK_SIZE
+            ));
+        }
+        Ok(chunk)
+    }
+    /// Writes every chunk changed since it was loaded to the directory the
+    /// grid was opened from.
+    pub fn save(&mut self) -> Result<(), String> {
+        let dir = self
+            .dir
+            .clone()
+            .ok_or_else(|| "This grid wasn't opened from a directory!".to_string())?;
+        for (coord, chunk) in self.chunks.get_mut().unwrap().iter_mut() {
+            if chunk.dirty {
+                let path = dir.join(coord.file_name());
+                fs::write(&path, bincode::serialize(chunk).unwrap())
+                    .map_err(|e| format!("Couldn't write {}: {}", path.display(), e))?;
+                chunk.dirty = false;
+                self.stored.insert(*coord);
+            }
+        }
+        Ok(())
+    }
+    /// Saves and then drops the chunks in memory, to be read again on demand.
+    pub fn unload(&mut self) -> Result<(), String> {
+        self.save()?;
+        self.chunks.get_mut().unwrap().clear();
+        Ok(())
+    }
+    /// Number of chunks held in memory.
+    pub fn loaded(&self) -> usize {
+        self.chunks.read().unwrap().len()
+    }
+    /// Every chunk in memory or on disk.
+    pub fn coords(&self) -> Vec<ChunkCoord> {
+        let mut coords: HashSet<ChunkCoord> = self.stored.clone();
+        coords.extend(self.chunks.read().unwrap().keys());
+        coords.into_iter().collect()
+    }
+    /// World coordinates of `Position::new(0, 0)`.
+    pub fn origin(&self) -> (i64, i64) {
+        self.bounds.map_or((0, 0), |(min, _)| min.origin())
+    }
+    pub fn dimension(&self) -> (usize, usize) {
+        self.bounds.map_or((0, 0), |(min, max)| {
+            Self::span(min, max).expect("The bounds are checked when they grow!")
+        })
+    }
+    /// Width and height in cells of the chunks from `min` to `max`, failing
+    /// if that is more than `MAX_CELLS`.
+    fn span(min: ChunkCoord, max: ChunkCoord) -> Result<(usize, usize), String> {
+        let cells = |from: i32, to: i32| {
+            (to as i64 - from as i64 + 1)
+                .checked_mul(CHUNK_SIZE as i64)
+                .and_then(|cells| usize::try_from(cells).ok())
+        };
+        let too_large = || format!("The chunks from {:?} to {:?} are too far apart!", min, max);
+        let width = cells(min.x, max.x).ok_or_else(too_large)?;
+        let height = cells(min.y, max.y).ok_or_else(too_large)?;
+        Grid::cell_count(width, height)?;
+        Ok((width, height))
+    }
+    /// The position the solvers use for a world cell, if it's inside the
+    /// known chunks.
+    pub fn to_position(&self, x: i64, y: i64) -> Option<Position> {
+        let (ox, oy) = self.origin();
+        let (width, height) = self.dimension();
+        let (x, y) = (x - ox, y - oy);
+        if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+            Some(Position::new(x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+    pub fn to_world(&self, pos: Position) -> (i64, i64) {
+        let (ox, oy) = self.origin();
+        (ox + pos.x as i64, oy + pos.y as i64)
+    }
+    /// Grows the area the solvers see to cover the world cell at `x`, `y`
+    /// without allocating its chunk.
+    pub fn include(&mut self, x: i64, y: i64) -> Result<(), String> {
+        self.extend(ChunkCoord::of(x, y)?.0)
+    }
+    pub fn is_blocked_at(&self, pos: Position) -> bool {
+        let (x, y) = self.to_world(pos);
+        self.is_blocked(x, y)
+    }
+    fn extend(&mut self, coord: ChunkCoord) -> Result<(), String> {
+        let (min, max) = match self.bounds {
+            Some((min, max)) => (
+                ChunkCoord::new(min.x.min(coord.x), min.y.min(coord.y)),
+                ChunkCoord::new(max.x.max(coord.x), max.y.max(coord.y)),
+            ),
+            None => (coord, coord),
+        };
+        Self::span(min, max)?;
+        self.bounds = Some((min, max));
+        Ok(())
+    }
+    fn path(&self, coord: ChunkCoord) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(coord.file_name()))
+    }
+}
+
+/// Copies the walls of a grid, placing its top left cell at the world origin.
+/// Fails for grids larger than `MAX_CELLS`.
+impl TryFrom<&Grid> for ChunkedGrid {
+    type Error = String;
+
+    fn try_from(grid: &Grid) -> Result<Self, Self::Error> {
+        let mut chunked = Self::new();
+        if !grid.is_empty() {
+            let corner = |v: usize| i64::try_from(v - 1).map_err(|e| e.to_string());
+            chunked.include(0, 0)?;
+            chunked.include(corner(grid.width)?, corner(grid.height)?)?;
+        }
+        for pos in grid.positions() {
+            if grid.terrain(pos) == Some(Cell::Block) {
+                // nothing is on disk to be loaded first, and the bounds cover it
+                let (coord, index) = ChunkCoord::of(pos.x as i64, pos.y as i64)?;
+                chunked.write(coord, index, true)?;
+            }
+        }
+        Ok(chunked)
+    }
+}
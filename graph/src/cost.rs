@@ -0,0 +1,133 @@
+use crate::{Move, Position, Stencil};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+
+/// A number `AStar`, `ParallelAStar` and `AStarBidirectional` add up costs
+/// in. Sums saturate at `MAX` instead of overflowing, so `MAX` also stands
+/// for a cost that was never reached.
+///
+/// The other solvers add up `usize` costs, saturating as well. A stencil move
+/// costs a whole number of units, though float costs take compass and knight
+/// moves at their exact length.
+pub trait CostType: Copy + Debug + Default + Ord + Send + Sync + 'static {
+    const ZERO: Self;
+    const MAX: Self;
+
+    /// A cost in the units of `Direction::g_cost`.
+    fn from_units(units: usize) -> Self;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn saturating_add(self, other: Self) -> Self {
+        self.checked_add(other).unwrap_or(Self::MAX)
+    }
+    /// What taking `step` costs.
+    fn of_move(step: &Move) -> Self {
+        Self::from_units(step.cost)
+    }
+    /// The estimate of the cost from `from` to `to` when moving by the moves
+    /// of `stencil`. It never overestimates, and never drops by more than a
+    /// move costs, so f-costs only grow as a search goes on.
+    fn estimate(stencil: &Stencil, diagonal: bool, from: Position, to: Position) -> Self {
+        Self::from_units(stencil.heuristic(diagonal, from, to))
+    }
+}
+
+macro_rules! integer_cost {
+    ($($int:ty),*) => {$(
+        impl CostType for $int {
+            const ZERO: Self = 0;
+            const MAX: Self = <$int>::MAX;
+
+            fn from_units(units: usize) -> Self {
+                Self::try_from(units).unwrap_or(Self::MAX)
+            }
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$int>::checked_add(self, other)
+            }
+        }
+    )*};
+}
+
+integer_cost!(u32, u64, usize);
+
+/// A float ordered by `total_cmp`, so that it can be a `CostType`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ordered<F>(pub F);
+
+macro_rules! float_cost {
+    ($($float:ty),*) => {$(
+        impl PartialEq for Ordered<$float> {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+
+        impl Eq for Ordered<$float> {}
+
+        impl Ord for Ordered<$float> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+
+        impl PartialOrd for Ordered<$float> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        /// Sums that are no longer finite count as overflowing. Moves whose
+        /// cost is their rounded length, like the compass and knight moves,
+        /// cost their exact length, so a diagonal step costs 10√2 instead of
+        /// 14. The estimate is the exact octile distance for the compass and
+        /// the straight line distance at the cheapest cost per cell of any
+        /// move otherwise.
+        impl CostType for Ordered<$float> {
+            const ZERO: Self = Ordered(0.);
+            const MAX: Self = Ordered(<$float>::MAX);
+
+            fn from_units(units: usize) -> Self {
+                Ordered(units as $float)
+            }
+            fn checked_add(self, other: Self) -> Option<Self> {
+                let sum = self.0 + other.0;
+                if sum.is_finite() {
+                    Some(Ordered(sum))
+                } else {
+                    None
+                }
+            }
+            fn of_move(step: &Move) -> Self {
+                if Move::euclidean(step.dx, step.dy) == *step {
+                    Ordered((step.length() * 10.) as $float)
+                } else {
+                    Self::from_units(step.cost)
+                }
+            }
+            fn estimate(stencil: &Stencil, diagonal: bool, from: Position, to: Position) -> Self {
+                let (dx, dy) = (from.x as f64 - to.x as f64, from.y as f64 - to.y as f64);
+                let (dx, dy) = (dx.abs(), dy.abs());
+                let units = match stencil {
+                    Stencil::Compass if diagonal => {
+                        10. * (2f64.sqrt() * dx.min(dy) + (dx - dy).abs())
+                    }
+                    Stencil::Compass => 10. * (dx + dy),
+                    _ => {
+                        let rate = stencil
+                            .moves(diagonal)
+                            .iter()
+                            .map(|step| Self::of_move(step).0 as f64 / step.length())
+                            .fold(f64::INFINITY, f64::min);
+                        if !rate.is_finite() {
+                            return Self::ZERO;
+                        }
+                        (dx * dx + dy * dy).sqrt() * rate
+                    }
+                };
+                Ordered(units as $float)
+            }
+        }
+    )*};
+}
+
+float_cost!(f32, f64);
@@ -0,0 +1,115 @@
+use crate::{CostType, Position};
+
+const NO_PARENT: usize = usize::MAX;
+
+/// Closed flags, g-costs and parents of a search over a grid of known size,
+/// kept in flat arrays indexed like the grid instead of hash sets and maps.
+///
+/// Every entry is stamped with the generation that wrote it, so `clear` only
+/// bumps the generation and the arrays are reused by the next search as is.
+/// Closed cells are listed as well, so listing them doesn't scan the arrays.
+#[derive(Debug, Clone, Default)]
+pub struct DenseState<C = usize> {
+    width: usize,
+    height: usize,
+    generation: u32,
+    seen: Vec<u32>,
+    closed: Vec<u32>,
+    closed_cells: Vec<Position>,
+    g_costs: Vec<C>,
+    parents: Vec<usize>,
+}
+
+impl<C: CostType> DenseState<C> {
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut state = Self::default();
+        state.fit(width, height);
+        state
+    }
+    pub fn dimension(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+    /// Sizes the arrays for a grid, forgetting everything if the size changed.
+    pub fn fit(&mut self, width: usize, height: usize) {
+        if self.dimension() == (width, height) && self.generation > 0 {
+            return;
+        }
+        let len = width * height;
+        self.width = width;
+        self.height = height;
+        self.generation = 1;
+        self.seen = vec![0; len];
+        self.closed = vec![0; len];
+        self.closed_cells.clear();
+        self.g_costs = vec![C::ZERO; len];
+        self.parents = vec![NO_PARENT; len];
+    }
+    /// Forgets the last search without touching the arrays, unless the
+    /// generation wraps around.
+    pub fn clear(&mut self) {
+        self.closed_cells.clear();
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            self.seen.iter_mut().for_each(|stamp| *stamp = 0);
+            self.closed.iter_mut().for_each(|stamp| *stamp = 0);
+            self.generation = 1;
+        }
+    }
+    pub fn contains(&self, pos: Position) -> bool {
+        self.index(pos).is_some()
+    }
+    /// Whether the current search has reached `pos` at all.
+    pub fn is_seen(&self, pos: Position) -> bool {
+        matches!(self.index(pos), Some(i) if self.seen[i] == self.generation)
+    }
+    pub fn g_cost(&self, pos: Position) -> Option<C> {
+        let i = self.index(pos)?;
+        if self.seen[i] == self.generation {
+            Some(self.g_costs[i])
+        } else {
+            None
+        }
+    }
+    pub fn parent(&self, pos: Position) -> Option<Position> {
+        let i = self.index(pos)?;
+        if self.seen[i] == self.generation && self.parents[i] != NO_PARENT {
+            Some(self.position(self.parents[i]))
+        } else {
+            None
+        }
+    }
+    pub fn set(&mut self, pos: Position, g_cost: C, parent: Option<Position>) {
+        if let Some(i) = self.index(pos) {
+            self.seen[i] = self.generation;
+            self.g_costs[i] = g_cost;
+            self.parents[i] = parent
+                .and_then(|parent| self.index(parent))
+                .unwrap_or(NO_PARENT);
+        }
+    }
+    pub fn close(&mut self, pos: Position) {
+        if let Some(i) = self.index(pos) {
+            if self.closed[i] != self.generation {
+                self.closed[i] = self.generation;
+                self.closed_cells.push(pos);
+            }
+        }
+    }
+    pub fn is_closed(&self, pos: Position) -> bool {
+        matches!(self.index(pos), Some(i) if self.closed[i] == self.generation)
+    }
+    /// Every cell closed by the current search, in the order they closed.
+    pub fn closed(&self) -> impl Iterator<Item = Position> + '_ {
+        self.closed_cells.iter().copied()
+    }
+    fn index(&self, pos: Position) -> Option<usize> {
+        if pos.x < self.width && pos.y < self.height {
+            Some(pos.y * self.width + pos.x)
+        } else {
+            None
+        }
+    }
+    fn position(&self, index: usize) -> Position {
+        Position::new(index % self.width, index / self.width)
+    }
+}
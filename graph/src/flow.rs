@@ -0,0 +1,74 @@
+use crate::{CornerCutting, Direction, GridType, MovementRange, Position};
+use serde::{Deserialize, Serialize};
+
+/// Distance from every cell to `target` together with the direction a unit
+/// standing on that cell should move in to follow the shortest path there.
+/// Cells that cannot reach the target have neither.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FlowField {
+    pub width: usize,
+    pub height: usize,
+    pub target: Position,
+    distance: Vec<Option<usize>>,
+    direction: Vec<Option<Direction>>,
+}
+
+impl FlowField {
+    pub fn new(
+        grid_type: GridType,
+        target: Position,
+        diagonal: bool,
+        corner_cutting: CornerCutting,
+    ) -> Self {
+        let (width, height) = match grid_type {
+            GridType::Full(grid) => grid.dimension(),
+            GridType::Set(set) => set.dimension(),
+            GridType::Chunked(chunks) => chunks.dimension(),
+        };
+        let range = MovementRange::new(grid_type, target, usize::MAX, diagonal, corner_cutting);
+        let mut field = Self {
+            width,
+            height,
+            target,
+            distance: vec![None; width * height],
+            direction: vec![None; width * height],
+        };
+        for (pos, reach) in range.iter() {
+            if let Some(i) = field.index(*pos) {
+                field.distance[i] = Some(reach.cost);
+                field.direction[i] = reach
+                    .parent
+                    .and_then(|parent| Direction::between(*pos, parent));
+            }
+        }
+        field
+    }
+    pub fn dimension(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+    pub fn distance(&self, pos: Position) -> Option<usize> {
+        self.index(pos).and_then(|i| self.distance[i])
+    }
+    pub fn direction(&self, pos: Position) -> Option<Direction> {
+        self.index(pos).and_then(|i| self.direction[i])
+    }
+    /// Follows the field from `start` until the target is reached.
+    pub fn path_from(&self, start: Position) -> Option<Vec<Position>> {
+        self.distance(start)?;
+        let mut path = vec![start];
+        let mut current = start;
+        while let Some(dir) = self.direction(current) {
+            let (x, y) = dir.get_coordinate(current.x as isize, current.y as isize);
+            current = Position::new(x as usize, y as usize);
+            path.push(current);
+        }
+        Some(path)
+    }
+    fn index(&self, pos: Position) -> Option<usize> {
+        if pos.x < self.width && pos.y < self.height {
+            Some(pos.y * self.width + pos.x)
+        } else {
+            None
+        }
+    }
+}
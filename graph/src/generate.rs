@@ -0,0 +1,330 @@
+use crate::{Cell, Grid, Position, Rng};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use strum_macros::{EnumIter, EnumString};
+
+/// Procedural map generators. Mazes carve passages between "rooms" that sit on
+/// even coordinates, so every room is reachable from every other one; caves
+/// keep only their largest open region. Start and end are always placed on
+/// cells that are connected to each other.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, EnumIter, EnumString, Serialize, Deserialize,
+)]
+pub enum Generator {
+    #[default]
+    Backtracker,
+    Prim,
+    Kruskal,
+    Division,
+    Cave,
+}
+
+type Room = (usize, usize);
+
+impl Generator {
+    /// Fills `grid` with a maze and places the end points, or returns `None`
+    /// when the grid has no cells to put them on.
+    pub fn generate(&self, grid: &mut Grid, seed: u64) -> Option<(Position, Position)> {
+        if grid.is_empty() {
+            return None;
+        }
+        let mut rng = Rng::new(seed);
+        let open = match self {
+            Generator::Backtracker => backtracker(grid, &mut rng),
+            Generator::Prim => prim(grid, &mut rng),
+            Generator::Kruskal => kruskal(grid, &mut rng),
+            Generator::Division => division(grid, &mut rng),
+            Generator::Cave => cave(grid, &mut rng),
+        };
+        place_end_points(grid, &open, &mut rng)
+    }
+}
+
+fn place_end_points(
+    grid: &mut Grid,
+    open: &[Position],
+    rng: &mut Rng,
+) -> Option<(Position, Position)> {
+    if open.is_empty() {
+        return None;
+    }
+    let start = open[rng.index(open.len())];
+    let mut target = start;
+    if open.len() > 1 {
+        while target == start {
+            target = open[rng.index(open.len())];
+        }
+    }
+    grid.set_start(start);
+    grid.set_end(target);
+    Some((start, target))
+}
+
+fn rooms(grid: &Grid) -> Room {
+    (grid.width.div_ceil(2), grid.height.div_ceil(2))
+}
+
+fn room_pos(room: Room) -> Position {
+    Position::new(room.0 * 2, room.1 * 2)
+}
+
+fn room_positions(grid: &Grid) -> Vec<Position> {
+    let (rw, rh) = rooms(grid);
+    let mut open = Vec::new();
+    for j in 0..rh {
+        for i in 0..rw {
+            open.push(room_pos((i, j)));
+        }
+    }
+    open
+}
+
+fn room_neighbours(room: Room, rooms: Room) -> Vec<Room> {
+    let (i, j) = room;
+    let mut neighbours = Vec::new();
+    if j > 0 {
+        neighbours.push((i, j - 1));
+    }
+    if i + 1 < rooms.0 {
+        neighbours.push((i + 1, j));
+    }
+    if j + 1 < rooms.1 {
+        neighbours.push((i, j + 1));
+    }
+    if i > 0 {
+        neighbours.push((i - 1, j));
+    }
+    neighbours
+}
+
+fn carve_between(grid: &mut Grid, a: Room, b: Room) {
+    let (a, b) = (room_pos(a), room_pos(b));
+    grid.set(a, Cell::Path);
+    grid.set(Position::new((a.x + b.x) / 2, (a.y + b.y) / 2), Cell::Path);
+    grid.set(b, Cell::Path);
+}
+
+fn backtracker(grid: &mut Grid, rng: &mut Rng) -> Vec<Position> {
+    grid.fill(Cell::Block);
+    let (rw, rh) = rooms(grid);
+    let mut visited = vec![false; rw * rh];
+    let first = (rng.index(rw), rng.index(rh));
+    let pos = room_pos(first);
+    grid.set(pos, Cell::Path);
+    visited[first.1 * rw + first.0] = true;
+    let mut stack = vec![first];
+    while let Some(&current) = stack.last() {
+        let unvisited: Vec<Room> = room_neighbours(current, (rw, rh))
+            .into_iter()
+            .filter(|n| !visited[n.1 * rw + n.0])
+            .collect();
+        if unvisited.is_empty() {
+            stack.pop();
+        } else {
+            let next = unvisited[rng.index(unvisited.len())];
+            visited[next.1 * rw + next.0] = true;
+            carve_between(grid, current, next);
+            stack.push(next);
+        }
+    }
+    room_positions(grid)
+}
+
+fn prim(grid: &mut Grid, rng: &mut Rng) -> Vec<Position> {
+    grid.fill(Cell::Block);
+    let (rw, rh) = rooms(grid);
+    let mut in_maze = vec![false; rw * rh];
+    let mut in_frontier = vec![false; rw * rh];
+    let first = (rng.index(rw), rng.index(rh));
+    let pos = room_pos(first);
+    grid.set(pos, Cell::Path);
+    in_maze[first.1 * rw + first.0] = true;
+    let mut frontier = Vec::new();
+    for n in room_neighbours(first, (rw, rh)) {
+        in_frontier[n.1 * rw + n.0] = true;
+        frontier.push(n);
+    }
+    while !frontier.is_empty() {
+        let room = frontier.swap_remove(rng.index(frontier.len()));
+        let neighbours = room_neighbours(room, (rw, rh));
+        let connected: Vec<&Room> = neighbours
+            .iter()
+            .filter(|n| in_maze[n.1 * rw + n.0])
+            .collect();
+        carve_between(grid, *connected[rng.index(connected.len())], room);
+        in_maze[room.1 * rw + room.0] = true;
+        for n in neighbours {
+            let i = n.1 * rw + n.0;
+            if !in_maze[i] && !in_frontier[i] {
+                in_frontier[i] = true;
+                frontier.push(n);
+            }
+        }
+    }
+    room_positions(grid)
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn kruskal(grid: &mut Grid, rng: &mut Rng) -> Vec<Position> {
+    grid.fill(Cell::Block);
+    let (rw, rh) = rooms(grid);
+    let mut parent: Vec<usize> = (0..rw * rh).collect();
+    let mut edges = Vec::new();
+    for j in 0..rh {
+        for i in 0..rw {
+            let pos = room_pos((i, j));
+            grid.set(pos, Cell::Path);
+            if i + 1 < rw {
+                edges.push(((i, j), (i + 1, j)));
+            }
+            if j + 1 < rh {
+                edges.push(((i, j), (i, j + 1)));
+            }
+        }
+    }
+    rng.shuffle(&mut edges);
+    for (a, b) in edges {
+        let root_a = find_root(&mut parent, a.1 * rw + a.0);
+        let root_b = find_root(&mut parent, b.1 * rw + b.0);
+        if root_a != root_b {
+            parent[root_a] = root_b;
+            carve_between(grid, a, b);
+        }
+    }
+    room_positions(grid)
+}
+
+fn division(grid: &mut Grid, rng: &mut Rng) -> Vec<Position> {
+    grid.fill(Cell::Path);
+    // an even dimension leaves a trailing line of non-room cells that would
+    // otherwise become a corridor around every wall
+    if grid.width & 1 == 0 {
+        for y in 0..grid.height {
+            grid.set(Position::new(grid.width - 1, y), Cell::Block);
+        }
+    }
+    if grid.height & 1 == 0 {
+        for x in 0..grid.width {
+            grid.set(Position::new(x, grid.height - 1), Cell::Block);
+        }
+    }
+    let (rw, rh) = rooms(grid);
+    // areas are half-open ranges of rooms: (x0, y0, x1, y1)
+    let mut areas = vec![(0, 0, rw, rh)];
+    while let Some((x0, y0, x1, y1)) = areas.pop() {
+        let (w, h) = (x1 - x0, y1 - y0);
+        if w < 2 && h < 2 {
+            continue;
+        }
+        let vertical = if w == h { rng.chance(0.5) } else { w > h };
+        if vertical {
+            let k = x0 + 1 + rng.index(w - 1);
+            let gap = y0 + rng.index(h);
+            for y in (y0 * 2)..(y1 * 2 - 1) {
+                if y != gap * 2 {
+                    grid.set(Position::new(k * 2 - 1, y), Cell::Block);
+                }
+            }
+            areas.push((x0, y0, k, y1));
+            areas.push((k, y0, x1, y1));
+        } else {
+            let k = y0 + 1 + rng.index(h - 1);
+            let gap = x0 + rng.index(w);
+            for x in (x0 * 2)..(x1 * 2 - 1) {
+                if x != gap * 2 {
+                    grid.set(Position::new(x, k * 2 - 1), Cell::Block);
+                }
+            }
+            areas.push((x0, y0, x1, k));
+            areas.push((x0, k, x1, y1));
+        }
+    }
+    room_positions(grid)
+}
+
+fn count_blocks_around(grid: &Grid, x: usize, y: usize) -> usize {
+    let mut count = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            let outside =
+                nx < 0 || ny < 0 || nx >= grid.width as isize || ny >= grid.height as isize;
+            if outside || grid.terrain(Position::new(nx as usize, ny as usize)) == Some(Cell::Block)
+            {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+fn largest_region(grid: &Grid) -> Vec<Position> {
+    let mut seen = HashSet::new();
+    let mut largest = Vec::new();
+    for start in grid.positions() {
+        if seen.contains(&start) || grid[start] == Cell::Block {
+            continue;
+        }
+        seen.insert(start);
+        let mut region = Vec::new();
+        let mut stack = vec![start];
+        while let Some(pos) = stack.pop() {
+            region.push(pos);
+            for next in grid.neighbours(pos, false) {
+                if grid[next] != Cell::Block && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        if region.len() > largest.len() {
+            largest = region;
+        }
+    }
+    largest
+}
+
+fn cave(grid: &mut Grid, rng: &mut Rng) -> Vec<Position> {
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let cell = if rng.chance(0.45) {
+                Cell::Block
+            } else {
+                Cell::Path
+            };
+            grid.set(Position::new(x, y), cell);
+        }
+    }
+    for _ in 0..5 {
+        let previous = grid.clone();
+        for y in 0..grid.height {
+            for x in 0..grid.width {
+                let blocks = count_blocks_around(&previous, x, y);
+                if blocks >= 5 {
+                    grid.set(Position::new(x, y), Cell::Block);
+                } else if blocks < 4 {
+                    grid.set(Position::new(x, y), Cell::Path);
+                }
+            }
+        }
+    }
+    let region = largest_region(grid);
+    if region.is_empty() {
+        grid.fill(Cell::Path);
+        return grid.positions().collect();
+    }
+    grid.fill(Cell::Block);
+    for pos in region.iter() {
+        grid.set(*pos, Cell::Path);
+    }
+    region
+}
@@ -1,9 +1,12 @@
-use crate::{is_odd, Cell, Cost, Direction, Grid, GridType, Node, Position};
+use crate::{
+    Cell, CornerCutting, Cost, CostKey, CostType, DenseState, Grid, GridType, Node, OpenList,
+    Position, Regions, Stencil,
+};
 use priority_queue::PriorityQueue;
 use rayon::prelude::*;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
-use strum::IntoEnumIterator;
 
 pub trait AStarTrait {
     fn top(&self) -> Option<Node>;
@@ -29,6 +32,18 @@ pub trait AStarTrait {
         }
         self.trace()
     }
+    /// Returns no path right away when `regions` shows that the target is
+    /// walled off from the start, instead of exhausting the open list.
+    fn solve_within(&mut self, grid: GridType, regions: &Regions) -> Vec<Position> {
+        let (start, target) = self.end_points();
+        if regions.connected(start, target) {
+            self.solve(grid)
+        } else {
+            Vec::new()
+        }
+    }
+    /// The path found, without start and target, from the target back to the
+    /// start.
     fn trace(&self) -> Vec<Position> {
         let mut path = Vec::new();
         if let Some(mut current) = self.top() {
@@ -43,43 +58,73 @@ pub trait AStarTrait {
     }
 }
 
+/// Searches over a full grid keep their closed cells, g-costs and parents in
+/// a `DenseState`, which `clear` empties for free, instead of `closed` and
+/// `costs`.
+///
+/// Costs add up in `usize` unless another `CostType` is picked with
+/// `with_cost_type`. The open list is a `PriorityQueue` unless another one
+/// is picked with `with_open_list`.
 #[derive(Clone)]
-pub struct AStar {
-    open: PriorityQueue<Node, Cost>,
+pub struct AStar<C: CostType = usize, Q = PriorityQueue<Position, Reverse<(C, C)>>> {
+    open: Q,
     closed: HashSet<Position>,
+    costs: HashMap<Position, (C, Option<Position>)>,
+    dense: DenseState<C>,
     start: Position,
     pub target: Position,
     pub diagonal: bool,
+    pub corner_cutting: CornerCutting,
+    pub stencil: Stencil,
 }
 
+/// Closes cells in a `DenseState` over a full grid, and in `closed` otherwise.
 #[derive(Clone)]
-pub struct ParallelAStar {
-    open: Arc<Mutex<PriorityQueue<Node, Cost>>>,
+pub struct ParallelAStar<C: CostType = usize> {
+    open: Arc<Mutex<PriorityQueue<Node, Cost<C>>>>,
     closed: Arc<Mutex<HashSet<Position>>>,
+    dense: Arc<Mutex<DenseState<C>>>,
     start: Position,
     pub target: Position,
     pub diagonal: bool,
+    pub corner_cutting: CornerCutting,
+    pub stencil: Stencil,
 }
 
 impl AStar {
     pub fn new(start: Position, target: Position, diagonal: bool) -> Self {
-        let mut open = PriorityQueue::new();
-        let start_node = Node::new_from_pos(start);
-        open.push(
-            start_node,
-            Cost {
-                g_cost: 0,
-                h_cost: start.h_cost(&target),
-            },
-        );
-        let closed = HashSet::new();
-        Self {
+        Self::with_cost_type(start, target, diagonal)
+    }
+}
+
+impl<C: CostType> AStar<C> {
+    pub fn with_cost_type(start: Position, target: Position, diagonal: bool) -> Self {
+        Self::with_open_list(start, target, diagonal, PriorityQueue::new())
+    }
+}
+
+impl<C: CostType, Q: OpenList<Position>> AStar<C, Q>
+where
+    Q::Key: CostKey<C>,
+{
+    /// Searches with `open` as the open list. F-costs never drop as the
+    /// search goes on, nor rise by more than twice the most expensive move
+    /// above the lowest one queued, so a `BucketQueue` of that span or a
+    /// `RadixHeap` can take `usize` costs.
+    pub fn with_open_list(start: Position, target: Position, diagonal: bool, open: Q) -> Self {
+        let mut a_star = Self {
             open,
-            closed,
+            closed: HashSet::new(),
+            costs: HashMap::new(),
+            dense: DenseState::default(),
             start,
             target,
             diagonal,
-        }
+            corner_cutting: CornerCutting::default(),
+            stencil: Stencil::default(),
+        };
+        a_star.clear();
+        a_star
     }
     pub fn set_start(&mut self, start: Position) {
         self.start = start;
@@ -90,50 +135,88 @@ impl AStar {
     }
     pub fn clear(&mut self) {
         self.open.clear();
-        let start_node = Node::new(self.start.x, self.start.y);
-        self.open.push(
-            start_node,
-            Cost {
-                g_cost: 0,
-                h_cost: self.start.h_cost(&self.target),
-            },
-        );
         self.closed.clear();
+        self.costs.clear();
+        self.dense.clear();
+        self.set(self.start, C::ZERO, None);
+        let cost = Cost {
+            g_cost: C::ZERO,
+            h_cost: C::estimate(&self.stencil, self.diagonal, self.start, self.target),
+        };
+        self.open.push(self.start, Q::Key::of(cost));
     }
     pub fn step(&mut self, grid: &mut Grid) -> bool {
         if let Some(open) = self.top() {
-            if !self.open.is_empty() {
-                if open.pos == self.target {
-                    return true;
-                } else {
-                    self.find(GridType::Full(&grid));
-                    for (each, _) in self.open.iter() {
-                        if self.not_start_nor_end(*&each.pos) {
-                            grid.set(each.pos.x, each.pos.y, Cell::Visiting);
-                        }
-                    }
-                    for each in self.closed.iter() {
-                        if self.not_start_nor_end(*each) {
-                            grid.set(each.x, each.y, Cell::Visited);
-                        }
+            if open.pos == self.target {
+                return true;
+            } else {
+                self.find(GridType::Full(&grid));
+                let (open_list, closed_list) = self.get_open_and_closed_list();
+                for each in open_list {
+                    if self.not_start_nor_end(each) {
+                        grid.set_overlay(each, Cell::Visiting);
                     }
-                    if self.not_start_nor_end(open.pos) {
-                        grid.set(open.pos.x, open.pos.y, Cell::ShortestPath);
+                }
+                for each in closed_list {
+                    if self.not_start_nor_end(each) {
+                        grid.set_overlay(each, Cell::Visited);
                     }
                 }
+                if self.not_start_nor_end(open.pos) {
+                    grid.set_overlay(open.pos, Cell::ShortestPath);
+                }
             }
         }
         false
     }
+    fn closed_list(&self) -> Vec<Position> {
+        let mut closed: Vec<Position> = self.closed.iter().copied().collect();
+        closed.extend(self.dense.closed());
+        closed
+    }
+    fn g_cost(&self, pos: Position) -> Option<C> {
+        if self.dense.contains(pos) {
+            self.dense.g_cost(pos)
+        } else {
+            self.costs.get(&pos).map(|(g_cost, _)| *g_cost)
+        }
+    }
+    fn parent(&self, pos: Position) -> Option<Position> {
+        if self.dense.contains(pos) {
+            self.dense.parent(pos)
+        } else {
+            self.costs.get(&pos).and_then(|(_, parent)| *parent)
+        }
+    }
+    fn set(&mut self, pos: Position, g_cost: C, parent: Option<Position>) {
+        if self.dense.contains(pos) {
+            self.dense.set(pos, g_cost, parent);
+        } else {
+            self.costs.insert(pos, (g_cost, parent));
+        }
+    }
+    fn is_closed(&self, pos: Position) -> bool {
+        if self.dense.contains(pos) {
+            self.dense.is_closed(pos)
+        } else {
+            self.closed.contains(&pos)
+        }
+    }
+    fn close(&mut self, pos: Position) {
+        if self.dense.contains(pos) {
+            self.dense.close(pos);
+        } else {
+            self.closed.insert(pos);
+        }
+    }
 }
 
-impl AStarTrait for AStar {
+impl<C: CostType, Q: OpenList<Position>> AStarTrait for AStar<C, Q>
+where
+    Q::Key: CostKey<C>,
+{
     fn top(&self) -> Option<Node> {
-        if let Some((node, _)) = self.open.peek() {
-            Some(node.clone())
-        } else {
-            None
-        }
+        self.open.peek().map(|(pos, _)| Node::new_from_pos(*pos))
     }
     fn diagonal(&self) -> bool {
         self.diagonal
@@ -142,53 +225,77 @@ impl AStarTrait for AStar {
         (self.start, self.target)
     }
     fn get_open_and_closed_list(&self) -> (Vec<Position>, Vec<Position>) {
-        let mut o = Vec::new();
-        let mut c = Vec::new();
-        for (each, _) in self.open.iter() {
-            o.push(each.pos);
-        }
-        for each in self.closed.iter() {
-            c.push(*each);
+        let open = self
+            .open
+            .iter()
+            .filter(|pos| !self.is_closed(**pos))
+            .copied()
+            .collect();
+        (open, self.closed_list())
+    }
+    fn trace(&self) -> Vec<Position> {
+        let mut path = Vec::new();
+        if let Some(top) = self.top() {
+            let mut current = top.pos;
+            while let Some(parent) = self.parent(current) {
+                if self.not_start_nor_end(current) {
+                    path.push(current);
+                }
+                current = parent;
+            }
         }
-        (o, c)
+        path
     }
     fn find(&mut self, grid_type: GridType) {
-        let (current_node, current_cost) = self.open.pop().unwrap();
-        self.closed.insert(current_node.pos);
-        if current_node.pos == self.target {
+        if let GridType::Full(grid) = grid_type {
+            if self.dense.dimension() != grid.dimension() {
+                // the start was set before the state fit the grid
+                let start = self.g_cost(self.start).map(|g_cost| (self.start, g_cost));
+                self.dense.fit(grid.width, grid.height);
+                if let Some((start, g_cost)) = start {
+                    self.set(start, g_cost, None);
+                }
+            }
+        }
+        let current = match self.open.pop() {
+            Some((pos, _)) => pos,
+            None => return,
+        };
+        // a cell pushed again at a lower cost was expanded then
+        if self.is_closed(current) {
             return;
         }
-        for (i, dir) in Direction::iter().enumerate() {
-            if is_odd(i) || self.diagonal {
-                let neighbour = match grid_type {
-                    GridType::Full(grid) => current_node.get_neighbour_from_grid(dir, grid),
-                    GridType::Set(set) => current_node.get_neighbour_from_set(dir, set),
-                };
-                if let Ok(mut neighbour) = neighbour {
-                    if !self.closed.contains(&neighbour.pos) {
-                        let cost = current_cost.g_cost + dir.g_cost();
-                        let h_cost = neighbour.pos.h_cost(&self.target);
-                        let neighbour_cost = Cost {
-                            g_cost: cost,
-                            h_cost,
-                        };
-                        let mut in_open = false;
-                        for (old_n, old_n_cost) in self.open.iter_mut() {
-                            if old_n.pos == neighbour.pos {
-                                if cost < old_n_cost.g_cost {
-                                    old_n.set_parent(current_node.clone());
-                                    *old_n_cost = neighbour_cost;
-                                }
-                                in_open = true;
-                                break;
-                            }
-                        }
-                        if !in_open {
-                            neighbour.set_parent(current_node.clone());
-                            self.open.push(neighbour, neighbour_cost);
-                        }
-                    }
+        self.close(current);
+        if current == self.target {
+            return;
+        }
+        let current_node = Node::new_from_pos(current);
+        let current_cost = self.g_cost(current).unwrap_or(C::ZERO);
+        let moves = self.stencil.moves(self.diagonal);
+        for step in moves.iter() {
+            let neighbour = match grid_type {
+                GridType::Full(grid) => {
+                    current_node.get_neighbour_from_grid(*step, grid, self.corner_cutting)
+                }
+                GridType::Set(set) => {
+                    current_node.get_neighbour_from_set(*step, set, self.corner_cutting)
+                }
+                GridType::Chunked(chunks) => {
+                    current_node.get_neighbour_from_chunks(*step, chunks, self.corner_cutting)
+                }
+            };
+            if let Ok(neighbour) = neighbour {
+                let pos = neighbour.pos;
+                let g_cost = current_cost.saturating_add(C::of_move(step));
+                if self.is_closed(pos) || matches!(self.g_cost(pos), Some(old) if old <= g_cost) {
+                    continue;
                 }
+                self.set(pos, g_cost, Some(current));
+                let cost = Cost {
+                    g_cost,
+                    h_cost: C::estimate(&self.stencil, self.diagonal, pos, self.target),
+                };
+                self.open.push(pos, Q::Key::of(cost));
             }
         }
     }
@@ -196,13 +303,19 @@ impl AStarTrait for AStar {
 
 impl ParallelAStar {
     pub fn new(start: Position, target: Position, diagonal: bool) -> Self {
+        Self::with_cost_type(start, target, diagonal)
+    }
+}
+
+impl<C: CostType> ParallelAStar<C> {
+    pub fn with_cost_type(start: Position, target: Position, diagonal: bool) -> Self {
         let mut open = PriorityQueue::new();
         let start_node = Node::new_from_pos(start);
         open.push(
             start_node,
             Cost {
-                g_cost: 0,
-                h_cost: start.h_cost(&target),
+                g_cost: C::ZERO,
+                h_cost: C::estimate(&Stencil::default(), diagonal, start, target),
             },
         );
         let open = Arc::new(Mutex::new(open));
@@ -210,13 +323,33 @@ impl ParallelAStar {
         Self {
             open,
             closed,
+            dense: Arc::new(Mutex::new(DenseState::default())),
             start,
             target,
             diagonal,
+            corner_cutting: CornerCutting::default(),
+            stencil: Stencil::default(),
+        }
+    }
+    fn is_closed(&self, pos: Position) -> bool {
+        let dense = self.dense.lock().unwrap();
+        if dense.contains(pos) {
+            dense.is_closed(pos)
+        } else {
+            self.closed.lock().unwrap().contains(&pos)
+        }
+    }
+    fn close(&self, pos: Position) {
+        let mut dense = self.dense.lock().unwrap();
+        if dense.contains(pos) {
+            dense.close(pos);
+        } else {
+            self.closed.lock().unwrap().insert(pos);
         }
     }
 }
-impl AStarTrait for ParallelAStar {
+
+impl<C: CostType> AStarTrait for ParallelAStar<C> {
     fn top(&self) -> Option<Node> {
         if let Some((node, _)) = self.open.lock().unwrap().peek() {
             Some(node.clone())
@@ -232,47 +365,40 @@ impl AStarTrait for ParallelAStar {
     }
     fn get_open_and_closed_list(&self) -> (Vec<Position>, Vec<Position>) {
         let mut o = Vec::new();
-        let c = Vec::new();
-        let clo = Arc::new(Mutex::new(c));
-        self.closed
-            .lock()
-            .unwrap()
-            .par_iter()
-            .for_each(|e| clo.lock().unwrap().push(*e));
         for (each, _) in self.open.lock().unwrap().iter() {
             o.push(each.pos);
         }
-        let c = Arc::try_unwrap(clo).unwrap().into_inner().unwrap();
+        let mut c: Vec<Position> = self.closed.lock().unwrap().iter().copied().collect();
+        c.extend(self.dense.lock().unwrap().closed());
         (o, c)
     }
     fn find(&mut self, grid_type: GridType) {
+        if let GridType::Full(grid) = grid_type {
+            self.dense.lock().unwrap().fit(grid.width, grid.height);
+        }
         let (current_node, current_cost) = self.open.lock().unwrap().pop().unwrap();
-        self.closed.lock().unwrap().insert(current_node.pos);
+        self.close(current_node.pos);
         if current_node.pos == self.target {
             return;
         }
-        let range = if self.diagonal { 0..8 } else { 0..4 };
-        range.into_par_iter().for_each(|i| {
-            let dir = if self.diagonal {
-                Direction::from(i as u8)
-            } else {
-                Direction::from((i * 2) as u8)
-            };
+        let moves = self.stencil.moves(self.diagonal);
+        moves.as_slice().par_iter().for_each(|step| {
             let neighbour = match grid_type {
-                GridType::Full(grid) => current_node.get_neighbour_from_grid(dir, grid),
-                GridType::Set(set) => current_node.get_neighbour_from_set(dir, set),
+                GridType::Full(grid) => {
+                    current_node.get_neighbour_from_grid(*step, grid, self.corner_cutting)
+                }
+                GridType::Set(set) => {
+                    current_node.get_neighbour_from_set(*step, set, self.corner_cutting)
+                }
+                GridType::Chunked(chunks) => {
+                    current_node.get_neighbour_from_chunks(*step, chunks, self.corner_cutting)
+                }
             };
             if let Ok(mut neighbour) = neighbour {
-                if !self
-                    .closed
-                    .lock()
-                    .unwrap()
-                    .par_iter()
-                    .any(|e| *e == neighbour.pos)
-                {
-                    //let g_cost = neighbour.pos.h_cost(&self.target);
-                    let cost = current_cost.g_cost + dir.g_cost();
-                    let h_cost = neighbour.pos.h_cost(&self.target);
+                if !self.is_closed(neighbour.pos) {
+                    let cost = current_cost.g_cost.saturating_add(C::of_move(step));
+                    let h_cost =
+                        C::estimate(&self.stencil, self.diagonal, neighbour.pos, self.target);
                     let neighbour_cost = Cost {
                         g_cost: cost,
                         h_cost,
@@ -1,8 +1,20 @@
-use crate::Position;
+use crate::{
+    is_odd, ChunkedGrid, CornerCutting, Direction, Generator, MovementRange, Position, Rng,
+};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Index;
+use std::str::FromStr;
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Eq, Hash)]
+/// The most cells a grid may have, so that a dimension read from a request or
+/// a file can't claim all the memory there is.
+pub const MAX_CELLS: usize = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Eq, Hash, Serialize, Deserialize)]
 pub enum Cell {
     Block,
     Path,
@@ -11,6 +23,7 @@ pub enum Cell {
     ShortestPath,
     Visiting,
     Visited,
+    Reachable,
 }
 
 impl Default for Cell {
@@ -20,6 +33,17 @@ impl Default for Cell {
 }
 
 impl Cell {
+    /// Walls and open ground, the cells stored in a grid's terrain layer.
+    pub fn is_terrain(&self) -> bool {
+        matches!(self, Cell::Block | Cell::Path)
+    }
+    pub fn is_end_point(&self) -> bool {
+        matches!(self, Cell::Start | Cell::End)
+    }
+    /// Search state drawn over the terrain, which never affects the search.
+    pub fn is_overlay(&self) -> bool {
+        !self.is_terrain() && !self.is_end_point()
+    }
     pub fn fill_color(&self) -> &'static str {
         match self {
             Cell::Block => "#111",
@@ -29,6 +53,7 @@ impl Cell {
             Cell::ShortestPath => "#aaa",
             Cell::Visiting => "#03c",
             Cell::Visited => "#a00",
+            Cell::Reachable => "#066",
         }
     }
     pub fn stroke_color(&self) -> &'static str {
@@ -40,11 +65,37 @@ impl Cell {
             Cell::ShortestPath => "#ccc",
             Cell::Visiting => "#05c",
             Cell::Visited => "#c00",
+            Cell::Reachable => "#088",
+        }
+    }
+    pub fn symbol(&self) -> char {
+        match self {
+            Cell::Block => '#',
+            Cell::Path => '.',
+            Cell::Start => 'S',
+            Cell::End => 'E',
+            Cell::ShortestPath => '*',
+            Cell::Visiting => 'o',
+            Cell::Visited => 'x',
+            Cell::Reachable => '+',
+        }
+    }
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '#' => Some(Cell::Block),
+            '.' => Some(Cell::Path),
+            'S' => Some(Cell::Start),
+            'E' => Some(Cell::End),
+            '*' => Some(Cell::ShortestPath),
+            'o' => Some(Cell::Visiting),
+            'x' => Some(Cell::Visited),
+            '+' => Some(Cell::Reachable),
+            _ => None,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct GridSet {
     pub width: usize,
     pub height: usize,
@@ -55,110 +106,420 @@ impl GridSet {
     pub fn dimension(&self) -> (usize, usize) {
         (self.width, self.height)
     }
+    /// Every position, in row-major order.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let (width, height) = self.dimension();
+        (0..height).flat_map(move |y| (0..width).map(move |x| Position::new(x, y)))
+    }
+    pub fn reachable(
+        &self,
+        origin: Position,
+        budget: usize,
+        diagonal: bool,
+        corner_cutting: CornerCutting,
+    ) -> MovementRange {
+        MovementRange::new(
+            GridType::Set(self),
+            origin,
+            budget,
+            diagonal,
+            corner_cutting,
+        )
+    }
+}
+
+impl From<&Grid> for GridSet {
+    fn from(grid: &Grid) -> Self {
+        let mut set = HashSet::new();
+        for pos in grid.positions() {
+            if grid.terrain(pos) == Some(Cell::Block) {
+                set.insert(pos);
+            }
+        }
+        Self {
+            width: grid.width,
+            height: grid.height,
+            set,
+        }
+    }
+}
+
+impl From<&GridSet> for Grid {
+    fn from(grid_set: &GridSet) -> Self {
+        let mut grid = Grid::new(grid_set.width, grid_set.height);
+        for pos in grid_set.set.iter() {
+            grid.set(*pos, Cell::Block);
+        }
+        grid
+    }
 }
 
 #[derive(Clone, Copy)]
 pub enum GridType<'a> {
     Full(&'a Grid),
     Set(&'a GridSet),
+    Chunked(&'a ChunkedGrid),
 }
 
-#[derive(Clone, Debug)]
+/// A map of walls and open ground, the search state drawn over it and the
+/// two end points, each kept apart so clearing a search never touches the map.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Cells", into = "Cells")]
 pub struct Grid {
     pub width: usize,
     pub height: usize,
+    terrain: Vec<Cell>,
+    overlay: Vec<Option<Cell>>,
+    start: Option<Position>,
+    end: Option<Position>,
+}
+
+/// The serialized form of a grid: one cell per position as the layers are
+/// seen from above, which is what version 2 saves contain.
+#[derive(Clone, Serialize, Deserialize)]
+struct Cells {
+    width: usize,
+    height: usize,
     data: Vec<Cell>,
 }
 
-impl std::ops::Deref for Grid {
-    type Target = Vec<Cell>;
+impl TryFrom<Cells> for Grid {
+    type Error = String;
+
+    fn try_from(cells: Cells) -> Result<Self, Self::Error> {
+        let len = Grid::cell_count(cells.width, cells.height)?;
+        if cells.data.len() != len {
+            return Err(format!(
+                "A {}x{} grid needs {} cells but {} were given!",
+                cells.width,
+                cells.height,
+                len,
+                cells.data.len()
+            ));
+        }
+        let mut grid = Grid::new(cells.width, cells.height);
+        let positions: Vec<Position> = grid.positions().collect();
+        for (pos, cell) in positions.into_iter().zip(cells.data) {
+            grid.set(pos, cell);
+        }
+        Ok(grid)
+    }
+}
+
+impl From<Grid> for Cells {
+    fn from(grid: Grid) -> Self {
+        Self {
+            width: grid.width,
+            height: grid.height,
+            data: grid.cells().collect(),
+        }
+    }
+}
+
+/// The cell drawn at a position, like `Grid::get` but panicking outside the
+/// grid.
+impl Index<Position> for Grid {
+    type Output = Cell;
+
+    fn index(&self, pos: Position) -> &Self::Output {
+        let index = self.index_or_panic(pos);
+        if self.start == Some(pos) {
+            &Cell::Start
+        } else if self.end == Some(pos) {
+            &Cell::End
+        } else {
+            self.overlay[index].as_ref().unwrap_or(&self.terrain[index])
+        }
+    }
+}
+
+/// One line per row, one `Cell::symbol` per column.
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, cell) in self.cells().enumerate() {
+            if i > 0 && i % self.width == 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", cell.symbol())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Grid {}x{}", self.width, self.height)?;
+        write!(f, "{}", self)
+    }
+}
+
+/// Parses the `Display` notation. Leading and trailing whitespace on each line
+/// and blank lines are ignored so grids can be written as indented literals.
+impl FromStr for Grid {
+    type Err = String;
 
-    fn deref(&self) -> &Self::Target {
-        &self.data
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let rows: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        let mut grid = Grid::new(width, rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            let length = row.chars().count();
+            if length != width {
+                return Err(format!(
+                    "Row {} has {} cells but the first row has {}!",
+                    y + 1,
+                    length,
+                    width
+                ));
+            }
+            for (x, symbol) in row.chars().enumerate() {
+                let cell = Cell::from_symbol(symbol).ok_or_else(|| {
+                    format!(
+                        "Row {}, column {}: unknown cell `{}`!",
+                        y + 1,
+                        x + 1,
+                        symbol
+                    )
+                })?;
+                grid.set(Position::new(x, y), cell);
+            }
+        }
+        Ok(grid)
     }
 }
 
 impl Grid {
     pub fn new(width: usize, height: usize) -> Self {
-        let mut data = Vec::new();
-        for _ in 0..(width * height) {
-            data.push(Cell::Path);
-        }
         Self {
             width,
             height,
-            data,
+            terrain: vec![Cell::Path; width * height],
+            overlay: vec![None; width * height],
+            start: None,
+            end: None,
         }
     }
+    /// How many cells a `width` by `height` grid has, or an error if that's
+    /// more than `MAX_CELLS`.
+    pub fn cell_count(width: usize, height: usize) -> Result<usize, String> {
+        width
+            .checked_mul(height)
+            .filter(|len| *len <= MAX_CELLS)
+            .ok_or_else(|| {
+                format!(
+                    "A {}x{} grid has more than the {} cells allowed!",
+                    width, height, MAX_CELLS
+                )
+            })
+    }
+    /// Removes the search state, and the walls too if `walls` is set. The end
+    /// points stay where they are.
     pub fn clear(&mut self, walls: bool) {
-        for i in 0..(self.width * self.height) {
-            let each = self.data.get_mut(i).unwrap();
-            let clear = match each {
-                Cell::Start | Cell::End | Cell::Block => *each == Cell::Block && walls,
-                _ => true,
-            };
-            if clear {
-                *each = Cell::Path;
-            }
+        if walls {
+            self.fill(Cell::Path);
+        } else {
+            self.clear_overlay();
+        }
+    }
+    pub fn clear_overlay(&mut self) {
+        for each in self.overlay.iter_mut() {
+            *each = None;
+        }
+    }
+    /// Sets every cell of the terrain to `cell`, which must be `Block` or `Path`,
+    /// and clears the search state.
+    pub fn fill(&mut self, cell: Cell) {
+        assert!(cell.is_terrain(), "{:?} isn't terrain!", cell);
+        for each in self.terrain.iter_mut() {
+            *each = cell;
         }
+        self.clear_overlay();
+    }
+    pub fn generate(&mut self, generator: Generator, seed: u64) -> Option<(Position, Position)> {
+        generator.generate(self, seed)
     }
     pub fn dimension(&self) -> (usize, usize) {
         (self.width, self.height)
     }
-    pub fn get(&self, column: usize, row: usize) -> Cell {
-        self.try_get(row, column)
-            .unwrap_or_else(|| panic!("Couldn't find at {} {}", row, column))
+    /// How many cells the grid has.
+    pub fn len(&self) -> usize {
+        self.terrain.len()
     }
-    pub fn try_get(&self, column: usize, row: usize) -> Option<Cell> {
-        if let Some(data) = self.data.get(row * self.width + column) {
-            Some(*data)
-        } else {
+    pub fn is_empty(&self) -> bool {
+        self.terrain.is_empty()
+    }
+    pub fn reachable(
+        &self,
+        origin: Position,
+        budget: usize,
+        diagonal: bool,
+        corner_cutting: CornerCutting,
+    ) -> MovementRange {
+        MovementRange::new(
+            GridType::Full(self),
+            origin,
+            budget,
+            diagonal,
+            corner_cutting,
+        )
+    }
+    pub fn start(&self) -> Option<Position> {
+        self.start
+    }
+    pub fn end(&self) -> Option<Position> {
+        self.end
+    }
+    pub fn end_points(&self) -> Option<(Position, Position)> {
+        Some((self.start?, self.end?))
+    }
+    /// Moves the start, opening the terrain under it.
+    pub fn set_start(&mut self, pos: Position) {
+        self.set_terrain(pos, Cell::Path);
+        self.start = Some(pos);
+    }
+    /// Moves the end, opening the terrain under it.
+    pub fn set_end(&mut self, pos: Position) {
+        self.set_terrain(pos, Cell::Path);
+        self.end = Some(pos);
+    }
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.x < self.width && pos.y < self.height
+    }
+    /// The cell drawn at a position: an end point, else the overlay, else the
+    /// terrain. `None` outside the grid.
+    pub fn get(&self, pos: Position) -> Option<Cell> {
+        if !self.contains(pos) {
             None
+        } else if self.start == Some(pos) {
+            Some(Cell::Start)
+        } else if self.end == Some(pos) {
+            Some(Cell::End)
+        } else {
+            self.overlay(pos).or_else(|| self.terrain(pos))
         }
     }
-    pub fn set(&mut self, column: usize, row: usize, cell: Cell) {
-        let index = row * self.width + column;
-        if index >= self.data.len() {
-            panic!("Couldn't find at {} {}", row, column);
-        } else {
-            self.data[index] = cell;
+    pub fn terrain(&self, pos: Position) -> Option<Cell> {
+        self.index(pos).map(|i| self.terrain[i])
+    }
+    pub fn overlay(&self, pos: Position) -> Option<Cell> {
+        self.index(pos).and_then(|i| self.overlay[i])
+    }
+    /// Every position, in row-major order.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let width = self.width;
+        (0..self.len()).map(move |i| Position::new(i % width, i / width))
+    }
+    /// Every cell as `get` sees it, in row-major order.
+    pub fn cells(&self) -> impl Iterator<Item = Cell> + '_ {
+        self.positions().map(move |pos| self[pos])
+    }
+    pub fn row(&self, y: usize) -> impl Iterator<Item = Cell> + '_ {
+        let width = if y < self.height { self.width } else { 0 };
+        (0..width).map(move |x| self[Position::new(x, y)])
+    }
+    pub fn column(&self, x: usize) -> impl Iterator<Item = Cell> + '_ {
+        let height = if x < self.width { self.height } else { 0 };
+        (0..height).map(move |y| self[Position::new(x, y)])
+    }
+    /// The positions next to `pos` that lie inside the grid, diagonal ones
+    /// only if `diagonal` is set. Walls are included.
+    pub fn neighbours(&self, pos: Position, diagonal: bool) -> impl Iterator<Item = Position> + '_ {
+        let (x, y) = (pos.x as isize, pos.y as isize);
+        Direction::iter()
+            .enumerate()
+            .filter(move |(i, _)| is_odd(*i) || diagonal)
+            .map(move |(_, dir)| dir.get_coordinate(x, y))
+            .filter(move |(x, y)| *x >= 0 && *y >= 0)
+            .map(|(x, y)| Position::new(x as usize, y as usize))
+            .filter(move |pos| self.contains(*pos))
+    }
+    pub fn position_of(&self, cell: Cell) -> Option<Position> {
+        match cell {
+            Cell::Start => self.start,
+            Cell::End => self.end,
+            _ => self.positions().find(|pos| self[*pos] == cell),
         }
     }
-    pub fn resize(&mut self, width: usize, height: usize) {
-        let mut new_data = Vec::new();
-        for row in 0..height {
-            for col in 0..width {
-                let mut done = false;
-                if let Some(cell) = self.try_get(col, row) {
-                    if cell != Cell::Start && cell != Cell::End {
-                        new_data.push(cell);
-                        done = true;
-                    }
+    pub fn positions_of(&self, cell: Cell) -> Vec<Position> {
+        self.positions().filter(|pos| self[*pos] == cell).collect()
+    }
+    /// Writes `cell` to the layer it belongs to. Terrain written over an end
+    /// point removes it, as does an end point being moved elsewhere.
+    pub fn set(&mut self, pos: Position, cell: Cell) {
+        match cell {
+            Cell::Start => self.set_start(pos),
+            Cell::End => self.set_end(pos),
+            Cell::Block | Cell::Path => {
+                self.set_terrain(pos, cell);
+                if self.start == Some(pos) {
+                    self.start = None;
                 }
-                if !done {
-                    new_data.push(Cell::Path);
+                if self.end == Some(pos) {
+                    self.end = None;
                 }
             }
+            _ => self.set_overlay(pos, cell),
+        }
+    }
+    /// Changes the map under a position, clearing any search state drawn on it.
+    pub fn set_terrain(&mut self, pos: Position, cell: Cell) {
+        assert!(cell.is_terrain(), "{:?} isn't terrain!", cell);
+        let index = self.index_or_panic(pos);
+        self.terrain[index] = cell;
+        self.overlay[index] = None;
+    }
+    pub fn set_overlay(&mut self, pos: Position, cell: Cell) {
+        assert!(cell.is_overlay(), "{:?} isn't search state!", cell);
+        let index = self.index_or_panic(pos);
+        self.overlay[index] = Some(cell);
+    }
+    /// Keeps the terrain that still fits; end points and search state are
+    /// dropped.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let mut resized = Grid::new(width, height);
+        for pos in resized.positions().collect::<Vec<_>>() {
+            if let Some(cell) = self.terrain(pos) {
+                resized.set_terrain(pos, cell);
+            }
+        }
+        *self = resized;
+    }
+    pub fn set_rand_start_n_end(&mut self, rng: &mut Rng) -> (Position, Position) {
+        let mut open: Vec<Position> = self
+            .positions()
+            .filter(|pos| self.terrain(*pos) != Some(Cell::Block))
+            .collect();
+        if open.is_empty() {
+            open = self.positions().collect();
         }
-        self.width = width;
-        self.height = height;
-        self.data = new_data;
-    }
-    pub fn set_rand_start_n_end(&mut self, rand: &dyn Fn() -> f64) -> (Position, Position) {
-        let (w, h) = (self.width, self.height);
-        let get_x_y = || {
-            let x = (rand() * w as f64) as usize;
-            let y = (rand() * h as f64) as usize;
-            (x, y)
-        };
-        let (x, y) = get_x_y();
-        let start = Position::new(x, y);
-        self.set(x, y, Cell::Start);
-        let (x, y) = get_x_y();
-        let target = Position::new(x, y);
-        self.set(x, y, Cell::End);
+        let start = open[rng.index(open.len())];
+        let mut target = start;
+        while target == start && open.len() > 1 {
+            target = open[rng.index(open.len())];
+        }
+        self.set_start(start);
+        self.set_end(target);
         (start, target)
     }
+    fn index(&self, pos: Position) -> Option<usize> {
+        if self.contains(pos) {
+            Some(pos.y * self.width + pos.x)
+        } else {
+            None
+        }
+    }
+    fn index_or_panic(&self, pos: Position) -> usize {
+        self.index(pos).unwrap_or_else(|| {
+            panic!(
+                "{} is outside the {}x{} grid!",
+                pos, self.width, self.height
+            )
+        })
+    }
     fn plot_line(&mut self, start: Position, target: Position, cell: Cell, high: bool) {
         let mut dx = target.x as isize - start.x as isize;
         let mut dy = target.y as isize - start.y as isize;
@@ -177,9 +538,10 @@ impl Grid {
         if high {
             let mut x = start.x as isize;
             for y in start.y..target.y {
-                if let Some(e_c) = self.try_get(x as usize, y) {
+                let pos = Position::new(x as usize, y);
+                if let Some(e_c) = self.get(pos) {
                     if e_c != Cell::Start && e_c != Cell::End {
-                        self.set(x as usize, y, cell);
+                        self.set(pos, cell);
                     }
                 }
                 if d > 0 {
@@ -192,9 +554,10 @@ impl Grid {
         } else {
             let mut y = start.y as isize;
             for x in start.x..target.x {
-                if let Some(e_c) = self.try_get(x, y as usize) {
+                let pos = Position::new(x, y as usize);
+                if let Some(e_c) = self.get(pos) {
                     if e_c != Cell::Start && e_c != Cell::End {
-                        self.set(x, y as usize, cell);
+                        self.set(pos, cell);
                     }
                 }
                 if d > 0 {
@@ -0,0 +1,195 @@
+use crate::{AStarConfig, CornerCutting, Direction, GridType, Node, Position};
+use priority_queue::PriorityQueue;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use strum::IntoEnumIterator;
+
+/// What changing heading between two moves costs on top of the moves, in the
+/// same units as `Direction::g_cost`. Costs add up saturating, so a huge turn
+/// cost makes the turn as good as forbidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TurnCosts {
+    /// Turning by 45°.
+    pub slight: usize,
+    /// Turning by 90°.
+    pub square: usize,
+    /// Turning by 135°.
+    pub sharp: usize,
+    /// Turning back the way the last move came, which `None` forbids.
+    pub reverse: Option<usize>,
+}
+
+impl Default for TurnCosts {
+    fn default() -> Self {
+        Self {
+            slight: 5,
+            square: 10,
+            sharp: 20,
+            reverse: None,
+        }
+    }
+}
+
+impl TurnCosts {
+    /// What moving `to` right after moving `from` costs on top of the move,
+    /// or `None` if the turn is forbidden.
+    pub fn cost(&self, from: Direction, to: Direction) -> Option<usize> {
+        match from.turn(to) {
+            0 => Some(0),
+            1 => Some(self.slight),
+            2 => Some(self.square),
+            3 => Some(self.sharp),
+            _ => self.reverse,
+        }
+    }
+}
+
+/// A cell of a path and the direction it was entered in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Heading {
+    pub pos: Position,
+    pub direction: Direction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadingPath {
+    /// Every cell after the start in order, ending with the target.
+    pub steps: Vec<Heading>,
+    /// The cost of the moves and the turns between them.
+    pub cost: usize,
+    /// How many times the heading changes along the way.
+    pub turns: usize,
+}
+
+impl HeadingPath {
+    /// The cells between start and target, both excluded.
+    pub fn path(&self) -> Vec<Position> {
+        let len = self.steps.len().saturating_sub(1);
+        self.steps[..len].iter().map(|step| step.pos).collect()
+    }
+}
+
+type State = (Position, Option<Direction>);
+
+/// A* over cells paired with the direction they were entered in, so that
+/// turning can cost extra and turning around can be ruled out. The start has
+/// no heading yet, so the first move is free to go any way.
+///
+/// Moves along the compass, ignoring the stencil, since headings are
+/// `Direction`s. The octile distance never overestimates as turns only add
+/// to the cost, so paths are optimal.
+#[derive(Clone)]
+pub struct HeadingAStar {
+    start: Position,
+    target: Position,
+    diagonal: bool,
+    corner_cutting: CornerCutting,
+    turns: TurnCosts,
+    g_costs: HashMap<State, usize>,
+    parents: HashMap<State, State>,
+    open: PriorityQueue<State, Reverse<(usize, usize)>>,
+    closed: HashSet<State>,
+}
+
+impl HeadingAStar {
+    pub fn new(config: AStarConfig, turns: TurnCosts) -> Self {
+        let mut search = Self {
+            start: config.start,
+            target: config.target,
+            diagonal: config.diagonal,
+            corner_cutting: config.corner_cutting,
+            turns,
+            g_costs: HashMap::new(),
+            parents: HashMap::new(),
+            open: PriorityQueue::new(),
+            closed: HashSet::new(),
+        };
+        let start = (search.start, None);
+        search.g_costs.insert(start, 0);
+        search.open.push(start, Reverse(search.key(start)));
+        search
+    }
+    pub fn solve(&mut self, grid: GridType) -> Option<HeadingPath> {
+        while let Some((state, _)) = self.open.pop() {
+            if state.0 == self.target {
+                return Some(self.trace(state));
+            }
+            self.closed.insert(state);
+            let g_cost = self.g_costs[&state];
+            let current = Node::new_from_pos(state.0);
+            for dir in Direction::iter() {
+                if !self.diagonal && dir.sides().is_some() {
+                    continue;
+                }
+                let turn = match state.1 {
+                    Some(heading) => match self.turns.cost(heading, dir) {
+                        Some(turn) => turn,
+                        None => continue,
+                    },
+                    None => 0,
+                };
+                let neighbour = match grid {
+                    GridType::Full(grid) => {
+                        current.get_neighbour_from_grid(dir, grid, self.corner_cutting)
+                    }
+                    GridType::Set(set) => {
+                        current.get_neighbour_from_set(dir, set, self.corner_cutting)
+                    }
+                    GridType::Chunked(chunks) => {
+                        current.get_neighbour_from_chunks(dir, chunks, self.corner_cutting)
+                    }
+                };
+                if let Ok(neighbour) = neighbour {
+                    let next = (neighbour.pos, Some(dir));
+                    if self.closed.contains(&next) {
+                        continue;
+                    }
+                    let n_cost = g_cost.saturating_add(dir.g_cost()).saturating_add(turn);
+                    if matches!(self.g_costs.get(&next), Some(old) if *old <= n_cost) {
+                        continue;
+                    }
+                    self.g_costs.insert(next, n_cost);
+                    self.parents.insert(next, state);
+                    let key = self.key(next);
+                    self.open.push(next, Reverse(key));
+                }
+            }
+        }
+        None
+    }
+    /// Every cell the search has reached in some heading.
+    pub fn get_open_and_closed_list(&self) -> (Vec<Position>, Vec<Position>) {
+        let closed: HashSet<Position> = self.closed.iter().map(|(pos, _)| *pos).collect();
+        let open: HashSet<Position> = self
+            .open
+            .iter()
+            .map(|((pos, _), _)| *pos)
+            .filter(|pos| !closed.contains(pos))
+            .collect();
+        (open.into_iter().collect(), closed.into_iter().collect())
+    }
+    /// Lowest f-cost first, and the one closest to the target among those.
+    fn key(&self, state: State) -> (usize, usize) {
+        let h_cost = state.0.octile(&self.target, self.diagonal);
+        (self.g_costs[&state].saturating_add(h_cost), h_cost)
+    }
+    fn trace(&self, target: State) -> HeadingPath {
+        let mut steps = Vec::new();
+        let mut current = target;
+        while let (pos, Some(direction)) = current {
+            steps.push(Heading { pos, direction });
+            current = self.parents[&current];
+        }
+        steps.reverse();
+        let turns = steps
+            .windows(2)
+            .filter(|pair| pair[0].direction != pair[1].direction)
+            .count();
+        HeadingPath {
+            steps,
+            cost: self.g_costs[&target],
+            turns,
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use crate::{Cell, CornerCutting, Grid, Position};
+use priority_queue::PriorityQueue;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+type Cluster = (usize, usize);
+
+/// Entrances at least this wide get a transition at either end instead of one
+/// in the middle.
+const WIDE_ENTRANCE: usize = 6;
+
+/// Hierarchical A*: the grid is split into square clusters, the cells where
+/// neighbouring clusters connect become the nodes of an abstract graph and the
+/// cost between the nodes of each cluster is computed up front. A query only
+/// searches that graph and then fills in the cells of the segments it uses.
+///
+/// Paths are close to optimal but not guaranteed to be, as they always cross
+/// clusters through one of the chosen transitions.
+#[derive(Debug, Clone)]
+pub struct Hpa {
+    pub cluster_size: usize,
+    pub diagonal: bool,
+    pub corner_cutting: CornerCutting,
+    width: usize,
+    height: usize,
+    blocked: Vec<bool>,
+    /// Transitions on the border of two clusters, west or north one first.
+    borders: HashMap<(Cluster, Cluster), Vec<(Position, Position)>>,
+    /// Costs between every two connected nodes of a cluster.
+    intra: HashMap<Cluster, Vec<(Position, Position, usize)>>,
+    graph: HashMap<Position, Vec<(Position, usize)>>,
+}
+
+impl Hpa {
+    pub fn new(
+        grid: &Grid,
+        cluster_size: usize,
+        diagonal: bool,
+        corner_cutting: CornerCutting,
+    ) -> Self {
+        let cluster_size = cluster_size.max(1);
+        let mut hpa = Self {
+            cluster_size,
+            diagonal,
+            corner_cutting,
+            width: grid.width,
+            height: grid.height,
+            blocked: grid
+                .positions()
+                .map(|pos| grid.terrain(pos) == Some(Cell::Block))
+                .collect(),
+            borders: HashMap::new(),
+            intra: HashMap::new(),
+            graph: HashMap::new(),
+        };
+        let clusters: Vec<Cluster> = hpa.clusters().collect();
+        for cluster in clusters.iter() {
+            hpa.build_borders(*cluster);
+        }
+        for cluster in clusters {
+            hpa.build_intra(cluster);
+        }
+        hpa.link();
+        hpa
+    }
+    pub fn dimension(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+    /// Number of nodes in the abstract graph.
+    pub fn len(&self) -> usize {
+        self.graph.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.graph.is_empty()
+    }
+    pub fn nodes(&self) -> impl Iterator<Item = &Position> {
+        self.graph.keys()
+    }
+    /// Recomputes the clusters around a cell whose terrain changed.
+    pub fn update(&mut self, grid: &Grid, pos: Position) {
+        if let Some(cell) = grid.terrain(pos) {
+            self.set_blocked(pos, cell == Cell::Block);
+            self.rebuild(vec![self.cluster_of(pos)]);
+        }
+    }
+    /// Applies every wall that changed since the abstraction was built,
+    /// recomputing only the clusters they are in. A grid of another size is
+    /// abstracted from scratch.
+    pub fn sync(&mut self, grid: &Grid) {
+        if grid.dimension() != self.dimension() {
+            *self = Self::new(grid, self.cluster_size, self.diagonal, self.corner_cutting);
+            return;
+        }
+        let mut changed = Vec::new();
+        for pos in grid.positions() {
+            if self.set_blocked(pos, grid.terrain(pos) == Some(Cell::Block)) {
+                changed.push(self.cluster_of(pos));
+            }
+        }
+        if !changed.is_empty() {
+            self.rebuild(changed);
+        }
+    }
+    /// The cells from `target` back to `start`, both excluded like the other
+    /// solvers, or nothing if there is no path.
+    pub fn solve(&self, start: Position, target: Position) -> Vec<Position> {
+        let mut path = self.find_path(start, target).unwrap_or_default();
+        path.retain(|pos| *pos != start && *pos != target);
+        path.reverse();
+        path
+    }
+    /// Every cell from `start` to `target`, both included.
+    pub fn find_path(&self, start: Position, target: Position) -> Option<Vec<Position>> {
+        if self.is_blocked(start) || self.is_blocked(target) {
+            return None;
+        }
+        let (start_cluster, target_cluster) = (self.cluster_of(start), self.cluster_of(target));
+        // connect the end points to the nodes of their clusters for this query
+        let mut extra: HashMap<Position, Vec<(Position, usize)>> = HashMap::new();
+        for (from, cluster) in &[(start, start_cluster), (target, target_cluster)] {
+            let costs = self.search_cluster(*cluster, *from);
+            let mut nodes = self.cluster_nodes(*cluster);
+            if start_cluster == target_cluster {
+                nodes.push(if *from == start { target } else { start });
+            }
+            for node in nodes {
+                if let Some((cost, _)) = costs.get(&node) {
+                    extra.entry(*from).or_default().push((node, *cost));
+                    extra.entry(node).or_default().push((*from, *cost));
+                }
+            }
+        }
+        let route = self.search_abstract(start, target, &extra)?;
+        let mut path = vec![start];
+        for pair in route.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            if self.cluster_of(from) == self.cluster_of(to) {
+                path.extend(self.refine(from, to)?.into_iter().skip(1));
+            } else {
+                path.push(to);
+            }
+        }
+        Some(path)
+    }
+    fn search_abstract(
+        &self,
+        start: Position,
+        target: Position,
+        extra: &HashMap<Position, Vec<(Position, usize)>>,
+    ) -> Option<Vec<Position>> {
+        let mut open = PriorityQueue::new();
+        let mut g_costs = HashMap::new();
+        let mut parents: HashMap<Position, Position> = HashMap::new();
+        let mut closed = HashSet::new();
+        g_costs.insert(start, 0);
+        open.push(start, Reverse(self.heuristic(start, target)));
+        while let Some((pos, _)) = open.pop() {
+            if pos == target {
+                let mut route = vec![pos];
+                let mut current = pos;
+                while let Some(parent) = parents.get(&current) {
+                    route.push(*parent);
+                    current = *parent;
+                }
+                route.reverse();
+                return Some(route);
+            }
+            closed.insert(pos);
+            let g_cost = g_costs[&pos];
+            let edges = self.graph.get(&pos).into_iter().flatten();
+            for (next, cost) in edges.chain(extra.get(&pos).into_iter().flatten()) {
+                if closed.contains(next) {
+                    continue;
+                }
+                let n_cost = usize::saturating_add(g_cost, *cost);
+                let cheaper = match g_costs.get(next) {
+                    Some(old) => n_cost < *old,
+                    None => true,
+                };
+                if cheaper {
+                    g_costs.insert(*next, n_cost);
+                    parents.insert(*next, pos);
+                    open.push_increase(
+                        *next,
+                        Reverse(n_cost.saturating_add(self.heuristic(*next, target))),
+                    );
+                }
+            }
+        }
+        None
+    }
+    /// The octile distance in the solvers' units, which never overestimates.
+    fn heuristic(&self, from: Position, to: Position) -> usize {
+        from.octile(&to, self.diagonal)
+    }
+    fn refine(&self, from: Position, to: Position) -> Option<Vec<Position>> {
+        let reached = self.search_cluster(self.cluster_of(from), from);
+        reached.get(&to)?;
+        let mut path = vec![to];
+        let mut current = to;
+        while let Some((_, Some(parent))) = reached.get(&current) {
+            path.push(*parent);
+            current = *parent;
+        }
+        path.reverse();
+        Some(path)
+    }
+    /// Dijkstra from `from` without leaving `cluster`.
+    fn search_cluster(
+        &self,
+        cluster: Cluster,
+        from: Position,
+    ) -> HashMap<Position, (usize, Option<Position>)> {
+        let (min, max) = self.bounds(cluster);
+        let mut reached = HashMap::new();
+        let mut open = PriorityQueue::new();
+        reached.insert(from, (0, None));
+        open.push(from, Reverse(0));
+        while let Some((pos, Reverse(cost))) = open.pop() {
+            for (next, step) in self.neighbours(pos) {
+                if next.x < min.x || next.y < min.y || next.x > max.x || next.y > max.y {
+                    continue;
+                }
+                let n_cost = usize::saturating_add(cost, step);
+                let cheaper = match reached.get(&next) {
+                    Some((old, _)) => n_cost < *old,
+                    None => true,
+                };
+                if cheaper {
+                    reached.insert(next, (n_cost, Some(pos)));
+                    open.push_increase(next, Reverse(n_cost));
+                }
+            }
+        }
+        reached
+    }
+    fn neighbours(&self, pos: Position) -> Vec<(Position, usize)> {
+        let mut neighbours = Vec::new();
+        for dy in -1..=1_isize {
+            for dx in -1..=1_isize {
+                let diagonal = dx != 0 && dy != 0;
+                if (dx == 0 && dy == 0) || (diagonal && !self.diagonal) {
+                    continue;
+                }
+                let (x, y) = (pos.x as isize + dx, pos.y as isize + dy);
+                if x < 0 || y < 0 {
+                    continue;
+                }
+                let next = Position::new(x as usize, y as usize);
+                let sides = (Position::new(next.x, pos.y), Position::new(pos.x, next.y));
+                let sides_open = (!self.is_blocked(sides.0), !self.is_blocked(sides.1));
+                if diagonal && !self.corner_cutting.allows(sides_open) {
+                    continue;
+                }
+                if next.x < self.width && next.y < self.height && !self.is_blocked(next) {
+                    neighbours.push((next, if diagonal { 14 } else { 10 }));
+                }
+            }
+        }
+        neighbours
+    }
+    fn rebuild(&mut self, changed: Vec<Cluster>) {
+        let changed: HashSet<Cluster> = changed.into_iter().collect();
+        let mut affected = changed.clone();
+        for cluster in changed.iter() {
+            self.build_borders(*cluster);
+            affected.extend(self.adjacent(*cluster));
+        }
+        for cluster in affected {
+            self.build_intra(cluster);
+        }
+        self.link();
+    }
+    /// Finds the transitions to the east and south neighbours of `cluster`,
+    /// and those of the west and north neighbours towards it.
+    fn build_borders(&mut self, cluster: Cluster) {
+        let (cx, cy) = cluster;
+        let mut pairs = vec![(cluster, (cx + 1, cy)), (cluster, (cx, cy + 1))];
+        if cx > 0 {
+            pairs.push(((cx - 1, cy), cluster));
+        }
+        if cy > 0 {
+            pairs.push(((cx, cy - 1), cluster));
+        }
+        for (a, b) in pairs {
+            if let Some(transitions) = self.transitions(a, b) {
+                self.borders.insert((a, b), transitions);
+            }
+        }
+    }
+    fn transitions(&self, a: Cluster, b: Cluster) -> Option<Vec<(Position, Position)>> {
+        let (columns, rows) = self.cluster_count();
+        if b.0 >= columns || b.1 >= rows {
+            return None;
+        }
+        let (min, max) = self.bounds(a);
+        let horizontal = b.0 > a.0;
+        let across = |i: usize| {
+            if horizontal {
+                (Position::new(max.x, i), Position::new(max.x + 1, i))
+            } else {
+                (Position::new(i, max.y), Position::new(i, max.y + 1))
+            }
+        };
+        let (first, last) = if horizontal {
+            (min.y, max.y)
+        } else {
+            (min.x, max.x)
+        };
+        let mut transitions = Vec::new();
+        let mut run = Vec::new();
+        for i in first..=last + 1 {
+            let open = i <= last && {
+                let (p, q) = across(i);
+                !self.is_blocked(p) && !self.is_blocked(q)
+            };
+            if open {
+                run.push(i);
+            } else if !run.is_empty() {
+                if run.len() >= WIDE_ENTRANCE {
+                    transitions.push(across(run[0]));
+                    transitions.push(across(run[run.len() - 1]));
+                } else {
+                    transitions.push(across(run[run.len() / 2]));
+                }
+                run.clear();
+            }
+        }
+        Some(transitions)
+    }
+    fn build_intra(&mut self, cluster: Cluster) {
+        let nodes = self.cluster_nodes(cluster);
+        let mut edges = Vec::new();
+        for (i, from) in nodes.iter().enumerate() {
+            let reached = self.search_cluster(cluster, *from);
+            for to in nodes.iter().skip(i + 1) {
+                if let Some((cost, _)) = reached.get(to) {
+                    edges.push((*from, *to, *cost));
+                }
+            }
+        }
+        self.intra.insert(cluster, edges);
+    }
+    fn link(&mut self) {
+        let mut graph: HashMap<Position, Vec<(Position, usize)>> = HashMap::new();
+        for (a, b) in self.borders.values().flatten() {
+            graph.entry(*a).or_default().push((*b, 10));
+            graph.entry(*b).or_default().push((*a, 10));
+        }
+        for (a, b, cost) in self.intra.values().flatten() {
+            graph.entry(*a).or_default().push((*b, *cost));
+            graph.entry(*b).or_default().push((*a, *cost));
+        }
+        self.graph = graph;
+    }
+    fn cluster_nodes(&self, cluster: Cluster) -> Vec<Position> {
+        let mut nodes: Vec<Position> = self
+            .adjacent(cluster)
+            .into_iter()
+            .flat_map(|other| {
+                let key = if other < cluster {
+                    (other, cluster)
+                } else {
+                    (cluster, other)
+                };
+                self.borders.get(&key).into_iter().flatten()
+            })
+            .flat_map(|(a, b)| vec![*a, *b])
+            .filter(|pos| self.cluster_of(*pos) == cluster)
+            .collect();
+        nodes.sort_by_key(|pos| (pos.y, pos.x));
+        nodes.dedup();
+        nodes
+    }
+    fn adjacent(&self, cluster: Cluster) -> Vec<Cluster> {
+        let (cx, cy) = cluster;
+        let (columns, rows) = self.cluster_count();
+        let mut adjacent = Vec::new();
+        if cx > 0 {
+            adjacent.push((cx - 1, cy));
+        }
+        if cy > 0 {
+            adjacent.push((cx, cy - 1));
+        }
+        if cx + 1 < columns {
+            adjacent.push((cx + 1, cy));
+        }
+        if cy + 1 < rows {
+            adjacent.push((cx, cy + 1));
+        }
+        adjacent
+    }
+    fn clusters(&self) -> impl Iterator<Item = Cluster> {
+        let (columns, rows) = self.cluster_count();
+        (0..rows).flat_map(move |cy| (0..columns).map(move |cx| (cx, cy)))
+    }
+    fn cluster_count(&self) -> (usize, usize) {
+        (
+            self.width.div_ceil(self.cluster_size),
+            self.height.div_ceil(self.cluster_size),
+        )
+    }
+    fn cluster_of(&self, pos: Position) -> Cluster {
+        (pos.x / self.cluster_size, pos.y / self.cluster_size)
+    }
+    /// Top left and bottom right cell of a cluster.
+    fn bounds(&self, cluster: Cluster) -> (Position, Position) {
+        let size = self.cluster_size;
+        let min = Position::new(cluster.0 * size, cluster.1 * size);
+        let max = Position::new(
+            (min.x + size).min(self.width) - 1,
+            (min.y + size).min(self.height) - 1,
+        );
+        (min, max)
+    }
+    /// Positions outside the grid count as blocked.
+    fn is_blocked(&self, pos: Position) -> bool {
+        match self.index(pos) {
+            Some(i) => self.blocked[i],
+            None => true,
+        }
+    }
+    /// Whether that changed anything.
+    fn set_blocked(&mut self, pos: Position, blocked: bool) -> bool {
+        match self.index(pos) {
+            Some(i) if self.blocked[i] != blocked => {
+                self.blocked[i] = blocked;
+                true
+            }
+            _ => false,
+        }
+    }
+    fn index(&self, pos: Position) -> Option<usize> {
+        if pos.x < self.width && pos.y < self.height {
+            Some(pos.y * self.width + pos.x)
+        } else {
+            None
+        }
+    }
+}
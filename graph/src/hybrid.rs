@@ -0,0 +1,338 @@
+use crate::{AStarConfig, Cell, CornerCutting, GridType, MovementRange, Position};
+use priority_queue::PriorityQueue;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::TAU;
+
+/// Spacing of the samples a motion primitive is checked for collisions at
+/// and traced with, in cells.
+const RESOLUTION: f64 = 0.1;
+
+/// The largest vehicle settings `HybridAStar::new` accepts, since each one
+/// multiplies the work done per expansion.
+pub const MAX_STEP: f64 = 16.;
+pub const MAX_STEERING_ANGLES: usize = 16;
+pub const MAX_HEADINGS: usize = 360;
+
+/// A continuous position and heading. Cell `(x, y)` spans `x..x + 1` and
+/// `y..y + 1`, and `theta` is in radians from the x axis towards the y axis,
+/// so it turns clockwise on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pose {
+    pub x: f64,
+    pub y: f64,
+    pub theta: f64,
+}
+
+impl Pose {
+    pub fn new(x: f64, y: f64, theta: f64) -> Self {
+        Self {
+            x,
+            y,
+            theta: theta.rem_euclid(TAU),
+        }
+    }
+    /// The pose at the centre of a cell.
+    pub fn centre(pos: Position, theta: f64) -> Self {
+        Self::new(pos.x as f64 + 0.5, pos.y as f64 + 0.5, theta)
+    }
+    /// The cell the pose is in, if it isn't left of or above the grid.
+    pub fn cell(&self) -> Option<Position> {
+        if self.x >= 0. && self.y >= 0. {
+            Some(Position::new(self.x as usize, self.y as usize))
+        } else {
+            None
+        }
+    }
+    /// Where driving `distance` forward with the steering held at
+    /// `curvature` leads, along an arc of radius `1 / curvature`.
+    pub fn drive(&self, curvature: f64, distance: f64) -> Self {
+        if curvature == 0. {
+            return Self::new(
+                self.x + distance * self.theta.cos(),
+                self.y + distance * self.theta.sin(),
+                self.theta,
+            );
+        }
+        let theta = self.theta + curvature * distance;
+        Self::new(
+            self.x + (theta.sin() - self.theta.sin()) / curvature,
+            self.y - (theta.cos() - self.theta.cos()) / curvature,
+            theta,
+        )
+    }
+}
+
+/// How a car-like robot can move, in cells.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Vehicle {
+    /// The tightest circle the vehicle can drive.
+    pub turning_radius: f64,
+    /// How far each motion primitive drives, up to `MAX_STEP`. Should be long
+    /// enough to leave a cell, or the search keeps landing on the same state.
+    pub step: f64,
+    /// How many headings are told apart within a cell, up to `MAX_HEADINGS`.
+    pub headings: usize,
+    /// Steering angles on either side of straight ahead, spread evenly up to
+    /// full lock. At most `MAX_STEERING_ANGLES`.
+    pub steering_angles: usize,
+    /// Extra cost of driving while steering, as a share of the distance.
+    pub steering_penalty: f64,
+    /// Extra cost of changing the steering angle between primitives.
+    pub change_penalty: f64,
+}
+
+impl Default for Vehicle {
+    fn default() -> Self {
+        Self {
+            turning_radius: 3.,
+            step: 1.5,
+            headings: 72,
+            steering_angles: 1,
+            steering_penalty: 0.05,
+            change_penalty: 0.2,
+        }
+    }
+}
+
+impl Vehicle {
+    /// The curvature of every steering angle, from full lock one way to full
+    /// lock the other.
+    fn curvatures(&self) -> impl Iterator<Item = f64> + '_ {
+        let n = self.steering_angles as isize;
+        (-n..=n).map(move |i| {
+            if n == 0 {
+                0.
+            } else {
+                i as f64 / n as f64 / self.turning_radius
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HybridPath {
+    /// Poses along the path from the start to within the target cell, close
+    /// enough together to draw as a curve.
+    pub poses: Vec<Pose>,
+    /// How far the vehicle drives.
+    pub length: f64,
+}
+
+type Key = (Position, usize);
+
+#[derive(Debug, Clone, Copy)]
+struct Primitive {
+    pose: Pose,
+    g_cost: f64,
+    length: f64,
+    curvature: f64,
+    parent: Option<usize>,
+}
+
+/// Hybrid A*: searches continuous poses reached by motion primitives, arcs
+/// of fixed length at each steering angle, but closes them by the cell and
+/// heading bin they end in so the search stays finite. The vehicle drives
+/// forward only, and is checked against blocks as a point.
+///
+/// The heuristic is the distance to the target around the walls, from a
+/// Dijkstra search over the cells, so paths are drivable but not always the
+/// shortest.
+#[derive(Clone)]
+pub struct HybridAStar {
+    /// Faces the target to begin with.
+    pub start: Pose,
+    target: Position,
+    vehicle: Vehicle,
+    primitives: Vec<Primitive>,
+    best: HashMap<Key, usize>,
+    open: PriorityQueue<usize, Reverse<u64>>,
+    closed: HashSet<Key>,
+}
+
+impl HybridAStar {
+    pub fn new(config: AStarConfig, vehicle: Vehicle) -> Result<Self, String> {
+        if !(vehicle.turning_radius > 0. && vehicle.step > 0.) || vehicle.headings == 0 {
+            return Err(format!(
+                "The vehicle {:?} needs a turning radius, a step and headings!",
+                vehicle
+            ));
+        }
+        if vehicle.step > MAX_STEP
+            || vehicle.steering_angles > MAX_STEERING_ANGLES
+            || vehicle.headings > MAX_HEADINGS
+        {
+            return Err(format!(
+                "The vehicle {:?} may step at most {} cells, with up to {} steering angles and {} headings!",
+                vehicle, MAX_STEP, MAX_STEERING_ANGLES, MAX_HEADINGS
+            ));
+        }
+        let penalties = [vehicle.steering_penalty, vehicle.change_penalty];
+        if !penalties
+            .iter()
+            .all(|penalty| penalty.is_finite() && *penalty >= 0.)
+        {
+            return Err(format!(
+                "The vehicle {:?} needs finite penalties that aren't negative!",
+                vehicle
+            ));
+        }
+        let (dx, dy) = (
+            config.target.x as f64 - config.start.x as f64,
+            config.target.y as f64 - config.start.y as f64,
+        );
+        Ok(Self {
+            start: Pose::centre(config.start, dy.atan2(dx)),
+            target: config.target,
+            vehicle,
+            primitives: Vec::new(),
+            best: HashMap::new(),
+            open: PriorityQueue::new(),
+            closed: HashSet::new(),
+        })
+    }
+    pub fn solve(&mut self, grid: GridType) -> Option<HybridPath> {
+        let distances =
+            MovementRange::new(grid, self.target, usize::MAX, true, CornerCutting::Never);
+        let heuristic = |pose: &Pose| {
+            pose.cell()
+                .and_then(|cell| distances.cost(&cell))
+                .map(|cost| cost as f64 / 10.)
+        };
+        self.primitives.clear();
+        self.best.clear();
+        self.open.clear();
+        self.closed.clear();
+        match self.start.cell() {
+            Some(cell) if !blocked(grid, cell) => (),
+            _ => return None,
+        }
+        self.push(
+            Primitive {
+                pose: self.start,
+                g_cost: 0.,
+                length: 0.,
+                curvature: 0.,
+                parent: None,
+            },
+            heuristic(&self.start)?,
+        );
+        while let Some((id, _)) = self.open.pop() {
+            let current = self.primitives[id];
+            let key = self.key(&current.pose);
+            if self.closed.contains(&key) {
+                continue;
+            }
+            self.closed.insert(key);
+            if key.0 == self.target {
+                return Some(self.trace(id));
+            }
+            let vehicle = self.vehicle;
+            for curvature in vehicle.curvatures() {
+                let mut length = 0.;
+                let mut reached = None;
+                let samples = (vehicle.step / RESOLUTION).ceil() as usize;
+                for i in 1..=samples {
+                    length = vehicle.step * i as f64 / samples as f64;
+                    let pose = current.pose.drive(curvature, length);
+                    match pose.cell() {
+                        Some(cell) if !blocked(grid, cell) => {
+                            reached = Some(pose);
+                            // stop short at the target, which a full step may drive past
+                            if cell == self.target {
+                                break;
+                            }
+                        }
+                        _ => {
+                            reached = None;
+                            break;
+                        }
+                    }
+                }
+                let pose = match reached {
+                    Some(pose) => pose,
+                    None => continue,
+                };
+                let h_cost = match heuristic(&pose) {
+                    Some(h_cost) => h_cost,
+                    None => continue,
+                };
+                let mut g_cost = current.g_cost + length;
+                if curvature != 0. {
+                    g_cost += length * vehicle.steering_penalty;
+                }
+                if current.parent.is_some() && curvature != current.curvature {
+                    g_cost += vehicle.change_penalty;
+                }
+                let key = self.key(&pose);
+                if self.closed.contains(&key)
+                    || matches!(self.best.get(&key), Some(old) if self.primitives[*old].g_cost <= g_cost)
+                {
+                    continue;
+                }
+                self.push(
+                    Primitive {
+                        pose,
+                        g_cost,
+                        length,
+                        curvature,
+                        parent: Some(id),
+                    },
+                    h_cost,
+                );
+            }
+        }
+        None
+    }
+    /// Every cell a closed pose is in.
+    pub fn closed_list(&self) -> Vec<Position> {
+        let cells: HashSet<Position> = self.closed.iter().map(|(pos, _)| *pos).collect();
+        cells.into_iter().collect()
+    }
+    fn push(&mut self, primitive: Primitive, h_cost: f64) {
+        let id = self.primitives.len();
+        self.best.insert(self.key(&primitive.pose), id);
+        self.primitives.push(primitive);
+        // scaled so fractions of a cell still order correctly
+        let key = ((primitive.g_cost + h_cost) * 1000.) as u64;
+        self.open.push(id, Reverse(key));
+    }
+    fn key(&self, pose: &Pose) -> Key {
+        let headings = self.vehicle.headings;
+        let bin = (pose.theta / TAU * headings as f64).round() as usize % headings;
+        (pose.cell().unwrap_or_default(), bin)
+    }
+    /// Drives every primitive again from its parent, keeping the samples.
+    fn trace(&self, id: usize) -> HybridPath {
+        let mut chain = Vec::new();
+        let mut current = Some(id);
+        while let Some(id) = current {
+            chain.push(self.primitives[id]);
+            current = self.primitives[id].parent;
+        }
+        chain.reverse();
+        let mut poses = vec![self.start];
+        let mut length = 0.;
+        for pair in chain.windows(2) {
+            let (from, to) = (pair[0].pose, pair[1]);
+            let samples = (to.length / RESOLUTION).ceil().max(1.) as usize;
+            for i in 1..=samples {
+                poses.push(from.drive(to.curvature, to.length * i as f64 / samples as f64));
+            }
+            length += to.length;
+        }
+        HybridPath { poses, length }
+    }
+}
+
+fn blocked(grid: GridType, pos: Position) -> bool {
+    match grid {
+        GridType::Full(grid) => grid.terrain(pos).unwrap_or(Cell::Block) == Cell::Block,
+        GridType::Set(set) => pos.x >= set.width || pos.y >= set.height || set.set.contains(&pos),
+        GridType::Chunked(chunks) => {
+            let (width, height) = chunks.dimension();
+            pos.x >= width || pos.y >= height || chunks.is_blocked_at(pos)
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{Cell, Grid, Position};
+use png::{ColorType, Decoder, Transformations};
+
+/// How pixels of an image are turned into cells, one pixel per cell.
+///
+/// Colours only pick out the end points, other pixels are walls or open ground
+/// by luminance.
+///
+/// Note: importing terrain weights from colours is out of scope. A cell is
+/// either open or a wall and every solver costs a move by its direction alone,
+/// so there is nothing a weight could be stored in or read by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImageConfig {
+    /// Pixels darker than this luminance (0 - 255) become `Cell::Block`.
+    pub threshold: u8,
+    /// The first pixel of this color becomes `Cell::Start`.
+    pub start: [u8; 3],
+    /// The first pixel of this color becomes `Cell::End`.
+    pub end: [u8; 3],
+    /// How far each channel may be from `start` or `end` and still match.
+    pub tolerance: u8,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 128,
+            start: [0, 255, 0],
+            end: [255, 255, 0],
+            tolerance: 64,
+        }
+    }
+}
+
+impl ImageConfig {
+    fn matches(&self, pixel: [u8; 3], color: [u8; 3]) -> bool {
+        pixel
+            .iter()
+            .zip(color.iter())
+            .all(|(p, c)| (*p as i16 - *c as i16).abs() <= self.tolerance as i16)
+    }
+    fn luminance(pixel: [u8; 3]) -> u8 {
+        let [r, g, b] = pixel;
+        ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000) as u8
+    }
+}
+
+impl Grid {
+    /// Decodes a PNG into a grid. Transparent pixels are open ground, as is
+    /// anything not dark enough for a wall, whatever its colour.
+    pub fn from_png(bytes: &[u8], config: &ImageConfig) -> Result<Self, String> {
+        let mut decoder = Decoder::new(bytes);
+        decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+        let (info, mut reader) = decoder
+            .read_info()
+            .map_err(|e| format!("Couldn't read the image header: {}", e))?;
+        let (width, height) = (info.width as usize, info.height as usize);
+        Grid::cell_count(width, height)?;
+        let mut buffer = vec![0; info.buffer_size()];
+        reader
+            .next_frame(&mut buffer)
+            .map_err(|e| format!("Couldn't decode the image: {}", e))?;
+        let channels = match info.color_type {
+            ColorType::Grayscale => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::RGB => 3,
+            ColorType::RGBA => 4,
+            ColorType::Indexed => {
+                return Err("Indexed images should have been expanded to RGB!".into());
+            }
+        };
+        let mut grid = Grid::new(width, height);
+        let (mut has_start, mut has_end) = (false, false);
+        for (y, line) in buffer.chunks(info.line_size).take(height).enumerate() {
+            for (x, pixel) in line.chunks(channels).take(width).enumerate() {
+                let (rgb, alpha) = match pixel {
+                    [v] => ([*v; 3], 255),
+                    [v, a] => ([*v; 3], *a),
+                    [r, g, b] => ([*r, *g, *b], 255),
+                    [r, g, b, a] => ([*r, *g, *b], *a),
+                    _ => unreachable!(),
+                };
+                let cell = if alpha < 128 {
+                    Cell::Path
+                } else if !has_start && config.matches(rgb, config.start) {
+                    has_start = true;
+                    Cell::Start
+                } else if !has_end && config.matches(rgb, config.end) {
+                    has_end = true;
+                    Cell::End
+                } else if ImageConfig::luminance(rgb) < config.threshold {
+                    Cell::Block
+                } else {
+                    Cell::Path
+                };
+                grid.set(Position::new(x, y), cell);
+            }
+        }
+        Ok(grid)
+    }
+}
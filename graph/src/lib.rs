@@ -1,38 +1,125 @@
 mod a_star;
+mod algorithm;
+mod anytime;
+mod bounded;
+mod chunk;
+mod cost;
+mod dense;
+mod flow;
+mod generate;
 mod grid;
+mod heading;
+mod hpa;
+mod hybrid;
 mod graph;
+mod image;
+mod macros;
+mod map;
 mod node;
+mod open;
+mod range;
+mod regions;
+mod rle;
+mod rng;
+mod save;
+mod stencil;
 
 pub use a_star::*;
+pub use algorithm::*;
+pub use anytime::*;
+pub use bounded::*;
+pub use chunk::*;
+pub use cost::*;
+pub use dense::*;
+pub use flow::*;
+pub use generate::*;
 pub use graph::*;
 pub use grid::*;
+pub use heading::*;
+pub use hpa::*;
+pub use hybrid::*;
+pub use image::*;
 pub use node::*;
+pub use open::*;
+pub use range::*;
+pub use regions::*;
+pub use rle::*;
+pub use rng::*;
+pub use save::*;
+pub use stencil::*;
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Request {
     pub dimension: (usize, usize),
-    pub blocked: Vec<Position>,
+    pub blocked: Blocked,
     pub a_star: AStarConfig,
+    /// The seed the maze was generated from. Together with `generator` the
+    /// walls are rebuilt from it and `blocked` is ignored, which the frontend
+    /// relies on as long as the maze is unedited.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub generator: Option<Generator>,
+    /// What Hybrid A* plans for, the default vehicle if not given.
+    #[serde(default)]
+    pub vehicle: Option<Vehicle>,
 }
 
 impl Default for Request {
     fn default() -> Self {
         Self {
             dimension: (0, 0),
-            blocked: Vec::new(),
+            blocked: Default::default(),
             a_star: Default::default(),
+            seed: None,
+            generator: None,
+            vehicle: None,
         }
     }
 }
 
+impl Request {
+    /// The blocked cells, rebuilt from the generator and seed when both are
+    /// given.
+    pub fn walls(&self) -> Result<Vec<Position>, String> {
+        match (self.generator, self.seed) {
+            (Some(generator), Some(seed)) => {
+                let (width, height) = self.dimension;
+                Grid::cell_count(width, height)?;
+                let mut grid = Grid::new(width, height);
+                grid.generate(generator, seed);
+                Ok(grid.positions_of(Cell::Block))
+            }
+            _ => self.blocked.positions(self.dimension),
+        }
+    }
+    pub fn grid_set(&self) -> Result<GridSet, String> {
+        let set: HashSet<Position> = self.walls()?.into_iter().collect();
+        Ok(GridSet {
+            width: self.dimension.0,
+            height: self.dimension.1,
+            set,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Response {
     pub path: Vec<Position>,
     pub open: Vec<Position>,
     pub closed: Vec<Position>,
     pub time: usize,
+    /// How many times the optimal cost the path may cost at most, when the
+    /// search was allowed to settle for less than the shortest path.
+    #[serde(default)]
+    pub bound: Option<f64>,
+    /// The path in order with the heading at each step, when the search
+    /// paid for turns.
+    #[serde(default)]
+    pub headings: Vec<Heading>,
 }
 
 impl Default for Response {
@@ -42,6 +129,8 @@ impl Default for Response {
             open: Vec::new(),
             closed: Vec::new(),
             time: 0,
+            bound: None,
+            headings: Vec::new(),
         }
     }
 }
@@ -0,0 +1,23 @@
+/// Builds a `Grid` from rows written in the ASCII notation of `Grid`'s
+/// `Display` implementation, panicking if a row is malformed.
+///
+/// ```
+/// use a_star_graph::{grid, Cell, Position};
+///
+/// let grid = grid![
+///     "S.#"
+///     "..E"
+/// ];
+/// assert_eq!(grid[Position::new(2, 0)], Cell::Block);
+/// ```
+#[macro_export]
+macro_rules! grid {
+    ($($row:literal)+) => {
+        concat!($($row, "\n"),+)
+            .parse::<$crate::Grid>()
+            .unwrap_or_else(|err| panic!("Invalid grid!\n{}", err))
+    };
+    ($($row:literal),+ $(,)?) => {
+        $crate::grid!($($row)+)
+    };
+}
@@ -0,0 +1,106 @@
+use crate::{Cell, Grid, GridSet, Position};
+use std::fmt::Write;
+
+/// Maps a MovingAI terrain character onto a cell. Swamp (`S`) is passable from
+/// regular ground; water (`W`) only from other water, which `Grid` can't
+/// express, so it is treated as a wall along with trees and out of bounds.
+fn terrain(c: char) -> Option<Cell> {
+    match c {
+        '.' | 'G' | 'S' => Some(Cell::Path),
+        '@' | 'O' | 'T' | 'W' => Some(Cell::Block),
+        _ => None,
+    }
+}
+
+impl Grid {
+    /// Parses a MovingAI benchmark `.map` file.
+    pub fn from_map(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate().map(|(i, l)| (i + 1, l.trim_end()));
+        let (mut width, mut height) = (None, None);
+        loop {
+            let (n, line) = lines
+                .next()
+                .ok_or_else(|| "Unexpected end of file before the `map` line!".to_string())?;
+            let mut words = line.split_whitespace();
+            let parse_size = |value: Option<&str>, key: &str| {
+                let value = value.ok_or_else(|| format!("Line {}: `{}` has no value!", n, key))?;
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Line {}: `{}` is not a valid {}!", n, value, key))
+            };
+            match words.next() {
+                Some("type") => (),
+                Some("height") => height = Some(parse_size(words.next(), "height")?),
+                Some("width") => width = Some(parse_size(words.next(), "width")?),
+                Some("map") => break,
+                Some(other) => {
+                    return Err(format!("Line {}: unknown header `{}`!", n, other));
+                }
+                None => (),
+            }
+        }
+        let width = width.ok_or_else(|| "The header is missing `width`!".to_string())?;
+        let height = height.ok_or_else(|| "The header is missing `height`!".to_string())?;
+        Grid::cell_count(width, height)?;
+        let mut grid = Grid::new(width, height);
+        for row in 0..height {
+            let (n, line) = lines.next().ok_or_else(|| {
+                format!(
+                    "Expected {} rows of terrain but found only {}!",
+                    height, row
+                )
+            })?;
+            let length = line.chars().count();
+            if length != width {
+                return Err(format!(
+                    "Line {}: expected {} columns but found {}!",
+                    n, width, length
+                ));
+            }
+            for (column, c) in line.chars().enumerate() {
+                let cell = terrain(c).ok_or_else(|| {
+                    format!(
+                        "Line {}, column {}: unknown terrain `{}`!",
+                        n,
+                        column + 1,
+                        c
+                    )
+                })?;
+                grid.set(Position::new(column, row), cell);
+            }
+        }
+        if let Some((n, _)) = lines.find(|(_, line)| !line.is_empty()) {
+            return Err(format!(
+                "Line {}: found more than the {} rows given in the header!",
+                n, height
+            ));
+        }
+        Ok(grid)
+    }
+    /// Writes the grid as an octile MovingAI `.map` file. Only walls are kept;
+    /// start, end and search state are written as open ground.
+    pub fn to_map(&self) -> String {
+        let mut map = String::new();
+        writeln!(map, "type octile").unwrap();
+        writeln!(map, "height {}", self.height).unwrap();
+        writeln!(map, "width {}", self.width).unwrap();
+        writeln!(map, "map").unwrap();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let blocked = self.terrain(Position::new(x, y)) == Some(Cell::Block);
+                map.push(if blocked { '@' } else { '.' });
+            }
+            map.push('\n');
+        }
+        map
+    }
+}
+
+impl GridSet {
+    pub fn from_map(text: &str) -> Result<Self, String> {
+        Grid::from_map(text).map(|grid| GridSet::from(&grid))
+    }
+    pub fn to_map(&self) -> String {
+        Grid::from(self).to_map()
+    }
+}
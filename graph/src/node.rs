@@ -1,11 +1,11 @@
-use crate::{Cell, Grid, GridSet};
+use crate::{Cell, ChunkedGrid, CostType, Grid, GridSet, Move};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
+use strum_macros::{EnumIter, EnumString};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub struct Position {
@@ -36,13 +36,24 @@ impl Position {
         (self.x as isize - another.x as isize).pow(2) as usize
             + (self.y as isize - another.y as isize).pow(2) as usize
     }
+    /// Cost of the cheapest unobstructed path to `another`, in the same units
+    /// as `Direction::g_cost`. Unlike `h_cost` it never overestimates.
+    pub fn octile(&self, another: &Self, diagonal: bool) -> usize {
+        let dx = (self.x as isize - another.x as isize).unsigned_abs();
+        let dy = (self.y as isize - another.y as isize).unsigned_abs();
+        if diagonal {
+            14 * dx.min(dy) + 10 * (dx.max(dy) - dx.min(dy))
+        } else {
+            10 * (dx + dy)
+        }
+    }
 }
 
 pub fn is_odd(num: usize) -> bool {
     num & 1 == 0
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Serialize, Deserialize)]
 pub enum Direction {
     North,
     NorthEast,
@@ -78,6 +89,29 @@ impl Direction {
             Direction::NorthWest => (x - 1, y - 1),
         }
     }
+    pub fn between(from: Position, to: Position) -> Option<Self> {
+        let (x, y) = (from.x as isize, from.y as isize);
+        Direction::iter().find(|dir| dir.get_coordinate(x, y) == (to.x as isize, to.y as isize))
+    }
+    pub fn offset(&self) -> (isize, isize) {
+        self.get_coordinate(0, 0)
+    }
+    /// The two straight directions a diagonal one passes between.
+    pub fn sides(&self) -> Option<(Self, Self)> {
+        match self {
+            Direction::NorthEast => Some((Direction::North, Direction::East)),
+            Direction::SouthEast => Some((Direction::South, Direction::East)),
+            Direction::SouthWest => Some((Direction::South, Direction::West)),
+            Direction::NorthWest => Some((Direction::North, Direction::West)),
+            _ => None,
+        }
+    }
+    /// How many eighths of a full turn lie between two headings, the short
+    /// way round.
+    pub fn turn(&self, to: Self) -> u8 {
+        let eighths = (to as u8 + 8 - *self as u8) % 8;
+        eighths.min(8 - eighths)
+    }
     pub fn g_cost(&self) -> usize {
         match self {
             Direction::North | Direction::East | Direction::South | Direction::West => 10,
@@ -89,39 +123,77 @@ impl Direction {
     }
 }
 
+/// Whether a diagonal move may squeeze past blocks on the cells it passes
+/// between.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, EnumIter, EnumString, Serialize, Deserialize,
+)]
+pub enum CornerCutting {
+    #[default]
+    Always,
+    /// Only if at least one of the two cells is open.
+    OneSideOpen,
+    /// Only if both cells are open.
+    Never,
+}
+
+impl CornerCutting {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CornerCutting::Always => "Always cut corners",
+            CornerCutting::OneSideOpen => "Cut corners with one side open",
+            CornerCutting::Never => "Never cut corners",
+        }
+    }
+    pub fn allows(&self, sides_open: (bool, bool)) -> bool {
+        match self {
+            CornerCutting::Always => true,
+            CornerCutting::OneSideOpen => sides_open.0 || sides_open.1,
+            CornerCutting::Never => sides_open.0 && sides_open.1,
+        }
+    }
+    /// Whether diagonal moves connect cells that straight ones don't, which
+    /// only happens when both cells passed between can be blocked.
+    pub fn connects_diagonally(&self) -> bool {
+        *self == CornerCutting::Always
+    }
+}
+
+/// The cost of reaching a node and the estimate from it to the target, in
+/// any `CostType`.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-pub struct Cost {
-    pub g_cost: usize,
-    pub h_cost: usize,
+pub struct Cost<C = usize> {
+    pub g_cost: C,
+    pub h_cost: C,
 }
 
-impl Default for Cost {
+impl<C: CostType> Default for Cost<C> {
     fn default() -> Self {
-        let max = usize::MAX;
         Cost {
-            g_cost: max,
-            h_cost: max,
+            g_cost: C::MAX,
+            h_cost: C::MAX,
         }
     }
 }
 
-impl Cost {
-    pub fn f_cost(&self) -> usize {
-        self.g_cost + self.h_cost
+impl<C: CostType> Cost<C> {
+    pub fn f_cost(&self) -> C {
+        self.g_cost.saturating_add(self.h_cost)
     }
 }
 
-impl Ord for Cost {
+/// The lowest f-cost is the greatest, so that the max-first `PriorityQueue`
+/// pops it, and the lowest h-cost breaks ties.
+impl<C: CostType> Ord for Cost<C> {
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.f_cost() <= other.f_cost() && self.h_cost < other.h_cost {
-            Ordering::Greater
-        } else {
-            Ordering::Less
-        }
+        other
+            .f_cost()
+            .cmp(&self.f_cost())
+            .then_with(|| other.h_cost.cmp(&self.h_cost))
     }
 }
 
-impl PartialOrd for Cost {
+impl<C: CostType> PartialOrd for Cost<C> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
@@ -159,13 +231,28 @@ impl Node {
     }
     pub fn get_neighbour(
         &self,
-        direction: Direction,
+        step: impl Into<Move>,
         bounds: (usize, usize),
+        corner_cutting: CornerCutting,
         check_block: &dyn Fn(Position) -> bool,
     ) -> Result<Self, String> {
-        let (x, y) = direction.get_coordinate(self.pos.x as isize, self.pos.y as isize);
+        let step = step.into();
+        let (x, y) = step.apply(self.pos);
         if Self::within_bounds((x, y), bounds) {
             let pos = Position::new(x as usize, y as usize);
+            if step.is_diagonal_step() {
+                let open = |(x, y): (isize, isize)| {
+                    Self::within_bounds((x, y), bounds)
+                        && check_block(Position::new(x as usize, y as usize))
+                };
+                let (x0, y0) = (self.pos.x as isize, self.pos.y as isize);
+                if !corner_cutting.allows((open((x0, y)), open((x, y0)))) {
+                    return Err(format!(
+                        "Moving by ({}, {}) from node at {:?} cuts a corner!",
+                        step.dx, step.dy, self.pos
+                    ));
+                }
+            }
             if check_block(pos) {
                 return Ok(Self {
                     pos,
@@ -173,8 +260,8 @@ impl Node {
                 });
             } else {
                 return Err(format!(
-                    "There is a block {:?} of node at {:?}!",
-                    direction, self.pos
+                    "There is a block at ({}, {}) from node at {:?}!",
+                    step.dx, step.dy, self.pos
                 ));
             }
         }
@@ -182,11 +269,12 @@ impl Node {
     }
     pub fn get_neighbour_from_grid(
         &self,
-        direction: Direction,
+        step: impl Into<Move>,
         grid: &Grid,
+        corner_cutting: CornerCutting,
     ) -> Result<Self, String> {
-        self.get_neighbour(direction, grid.dimension(), &|pos| {
-            if let Some(cell) = grid.try_get(pos.x, pos.y) {
+        self.get_neighbour(step, grid.dimension(), corner_cutting, &|pos| {
+            if let Some(cell) = grid.terrain(pos) {
                 cell != Cell::Block
             } else {
                 false
@@ -195,13 +283,24 @@ impl Node {
     }
     pub fn get_neighbour_from_set(
         &self,
-        direction: Direction,
+        step: impl Into<Move>,
         grid: &GridSet,
+        corner_cutting: CornerCutting,
     ) -> Result<Self, String> {
-        self.get_neighbour(direction, grid.dimension(), &|pos| {
+        self.get_neighbour(step, grid.dimension(), corner_cutting, &|pos| {
             !grid.set.par_iter().any(|e| *e == pos)
         })
     }
+    pub fn get_neighbour_from_chunks(
+        &self,
+        step: impl Into<Move>,
+        grid: &ChunkedGrid,
+        corner_cutting: CornerCutting,
+    ) -> Result<Self, String> {
+        self.get_neighbour(step, grid.dimension(), corner_cutting, &|pos| {
+            !grid.is_blocked_at(pos)
+        })
+    }
     fn within_bounds(pos: (isize, isize), dimension: (usize, usize)) -> bool {
         let (x, y) = pos;
         let (width, height) = dimension;
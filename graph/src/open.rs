@@ -0,0 +1,220 @@
+use crate::{Cost, CostType};
+use priority_queue::PriorityQueue;
+use std::cmp::Reverse;
+use std::hash::Hash;
+use std::mem;
+
+/// A min-queue for the open list of a search. Items may be pushed again with
+/// a lower key instead of being updated in place, so a search has to skip
+/// items it pops with a key above the best one it knows of.
+pub trait OpenList<T> {
+    type Key: Copy + Ord;
+
+    fn push(&mut self, item: T, key: Self::Key);
+    /// Removes an item with the lowest key.
+    fn pop(&mut self) -> Option<(T, Self::Key)>;
+    /// The item `pop` would remove next.
+    fn peek(&self) -> Option<(&T, Self::Key)>;
+    /// Every queued item, in no particular order.
+    fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_>;
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn clear(&mut self);
+}
+
+/// The general purpose heap the solvers use by default. Pushing an item that
+/// is already queued lowers its key rather than adding it twice.
+impl<T: Hash + Eq, K: Copy + Ord> OpenList<T> for PriorityQueue<T, Reverse<K>> {
+    type Key = K;
+
+    fn push(&mut self, item: T, key: K) {
+        self.push_increase(item, Reverse(key));
+    }
+    fn pop(&mut self) -> Option<(T, K)> {
+        PriorityQueue::pop(self).map(|(item, Reverse(key))| (item, key))
+    }
+    fn peek(&self) -> Option<(&T, K)> {
+        PriorityQueue::peek(self).map(|(item, Reverse(key))| (item, *key))
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(PriorityQueue::iter(self).map(|(item, _)| item))
+    }
+    fn len(&self) -> usize {
+        PriorityQueue::len(self)
+    }
+    fn clear(&mut self) {
+        PriorityQueue::clear(self)
+    }
+}
+
+/// Dial's bucket queue: one bucket per key in a ring that only has to span
+/// the largest jump between the lowest queued key and any key pushed, which
+/// for a Dijkstra search is the most expensive move.
+#[derive(Debug, Clone)]
+pub struct BucketQueue<T> {
+    buckets: Vec<Vec<T>>,
+    lowest: Option<usize>,
+    len: usize,
+}
+
+impl<T> BucketQueue<T> {
+    /// Keys pushed can't be below the last key popped, or the first key
+    /// pushed until then, nor more than `span` above it.
+    pub fn new(span: usize) -> Self {
+        Self {
+            buckets: (0..=span).map(|_| Vec::new()).collect(),
+            lowest: None,
+            len: 0,
+        }
+    }
+}
+
+impl<T> OpenList<T> for BucketQueue<T> {
+    type Key = usize;
+
+    fn push(&mut self, item: T, key: usize) {
+        let lowest = *self.lowest.get_or_insert(key);
+        assert!(
+            key >= lowest && key - lowest < self.buckets.len(),
+            "Key {} is outside the span of the bucket queue starting at {}!",
+            key,
+            lowest
+        );
+        let index = key % self.buckets.len();
+        self.buckets[index].push(item);
+        self.len += 1;
+    }
+    fn pop(&mut self) -> Option<(T, usize)> {
+        if self.len == 0 {
+            return None;
+        }
+        let lowest = self.lowest.as_mut()?;
+        loop {
+            let index = *lowest % self.buckets.len();
+            if let Some(item) = self.buckets[index].pop() {
+                self.len -= 1;
+                return Some((item, *lowest));
+            }
+            *lowest += 1;
+        }
+    }
+    fn peek(&self) -> Option<(&T, usize)> {
+        let lowest = self.lowest?;
+        (lowest..lowest + self.buckets.len()).find_map(|key| {
+            self.buckets[key % self.buckets.len()]
+                .last()
+                .map(|item| (item, key))
+        })
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.buckets.iter().flatten())
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn clear(&mut self) {
+        self.buckets.iter_mut().for_each(Vec::clear);
+        self.lowest = None;
+        self.len = 0;
+    }
+}
+
+/// A radix heap: items are kept in buckets by the highest bit in which their
+/// key differs from the last key popped, and a bucket is only sorted out once
+/// every lower one is empty. Keys must never go below the last key popped,
+/// which holds for Dijkstra and for A* with a consistent heuristic.
+#[derive(Debug, Clone)]
+pub struct RadixHeap<T> {
+    buckets: Vec<Vec<(usize, T)>>,
+    last: usize,
+    len: usize,
+}
+
+impl<T> Default for RadixHeap<T> {
+    fn default() -> Self {
+        Self {
+            buckets: (0..=usize::BITS).map(|_| Vec::new()).collect(),
+            last: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T> RadixHeap<T> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+    fn bucket(&self, key: usize) -> usize {
+        (usize::BITS - (key ^ self.last).leading_zeros()) as usize
+    }
+}
+
+impl<T> OpenList<T> for RadixHeap<T> {
+    type Key = usize;
+
+    fn push(&mut self, item: T, key: usize) {
+        assert!(
+            key >= self.last,
+            "Key {} is below the last key popped from the radix heap, {}!",
+            key,
+            self.last
+        );
+        let bucket = self.bucket(key);
+        self.buckets[bucket].push((key, item));
+        self.len += 1;
+    }
+    fn pop(&mut self) -> Option<(T, usize)> {
+        if self.buckets[0].is_empty() {
+            let index = self.buckets.iter().position(|bucket| !bucket.is_empty())?;
+            let bucket = mem::take(&mut self.buckets[index]);
+            self.last = bucket.iter().map(|(key, _)| *key).min().unwrap();
+            for (key, item) in bucket {
+                let index = self.bucket(key);
+                self.buckets[index].push((key, item));
+            }
+        }
+        let (key, item) = self.buckets[0].pop()?;
+        self.len -= 1;
+        Some((item, key))
+    }
+    fn peek(&self) -> Option<(&T, usize)> {
+        // the last of the lowest keys is what `pop` sorts to the top
+        let bucket = self.buckets.iter().find(|bucket| !bucket.is_empty())?;
+        bucket
+            .iter()
+            .rev()
+            .min_by_key(|(key, _)| *key)
+            .map(|(key, item)| (item, *key))
+    }
+    fn iter(&self) -> Box<dyn Iterator<Item = &T> + '_> {
+        Box::new(self.buckets.iter().flatten().map(|(_, item)| item))
+    }
+    fn len(&self) -> usize {
+        self.len
+    }
+    fn clear(&mut self) {
+        self.buckets.iter_mut().for_each(Vec::clear);
+        self.last = 0;
+        self.len = 0;
+    }
+}
+
+/// What `AStar` keys its open list by: the f-cost alone for the integer
+/// queues, or the f-cost with the h-cost breaking ties.
+pub trait CostKey<C: CostType>: Copy + Ord {
+    fn of(cost: Cost<C>) -> Self;
+}
+
+impl CostKey<usize> for usize {
+    fn of(cost: Cost<usize>) -> Self {
+        cost.f_cost()
+    }
+}
+
+impl<C: CostType> CostKey<C> for (C, C) {
+    fn of(cost: Cost<C>) -> Self {
+        (cost.f_cost(), cost.h_cost)
+    }
+}
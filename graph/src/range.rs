@@ -0,0 +1,133 @@
+use crate::{is_odd, CornerCutting, Direction, GridType, Node, OpenList, Position};
+use priority_queue::PriorityQueue;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use strum::IntoEnumIterator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reach {
+    pub cost: usize,
+    pub parent: Option<Position>,
+}
+
+/// Every cell reachable from `origin` without spending more than `budget`,
+/// found with a Dijkstra search that stops expanding once the budget runs out.
+#[derive(Debug, Clone)]
+pub struct MovementRange {
+    pub origin: Position,
+    pub budget: usize,
+    reached: HashMap<Position, Reach>,
+}
+
+impl MovementRange {
+    pub fn new(
+        grid_type: GridType,
+        origin: Position,
+        budget: usize,
+        diagonal: bool,
+        corner_cutting: CornerCutting,
+    ) -> Self {
+        let open: PriorityQueue<Position, Reverse<usize>> = PriorityQueue::new();
+        Self::with_open_list(grid_type, origin, budget, diagonal, corner_cutting, open)
+    }
+    /// Like `new`, searching with the given, empty, open list.
+    pub fn with_open_list<Q: OpenList<Position, Key = usize>>(
+        grid_type: GridType,
+        origin: Position,
+        budget: usize,
+        diagonal: bool,
+        corner_cutting: CornerCutting,
+        mut open: Q,
+    ) -> Self {
+        let mut reached: HashMap<Position, Reach> = HashMap::new();
+        reached.insert(
+            origin,
+            Reach {
+                cost: 0,
+                parent: None,
+            },
+        );
+        open.push(origin, 0);
+        while let Some((pos, cost)) = open.pop() {
+            if cost > reached[&pos].cost {
+                continue;
+            }
+            let current = Node::new_from_pos(pos);
+            for (i, dir) in Direction::iter().enumerate() {
+                if !is_odd(i) && !diagonal {
+                    continue;
+                }
+                let neighbour = match grid_type {
+                    GridType::Full(grid) => {
+                        current.get_neighbour_from_grid(dir, grid, corner_cutting)
+                    }
+                    GridType::Set(set) => current.get_neighbour_from_set(dir, set, corner_cutting),
+                    GridType::Chunked(chunks) => {
+                        current.get_neighbour_from_chunks(dir, chunks, corner_cutting)
+                    }
+                };
+                if let Ok(neighbour) = neighbour {
+                    let n_cost = cost.saturating_add(dir.g_cost());
+                    if n_cost > budget {
+                        continue;
+                    }
+                    let cheaper = match reached.get(&neighbour.pos) {
+                        Some(reach) => n_cost < reach.cost,
+                        None => true,
+                    };
+                    if cheaper {
+                        reached.insert(
+                            neighbour.pos,
+                            Reach {
+                                cost: n_cost,
+                                parent: Some(pos),
+                            },
+                        );
+                        open.push(neighbour.pos, n_cost);
+                    }
+                }
+            }
+        }
+        Self {
+            origin,
+            budget,
+            reached,
+        }
+    }
+    pub fn get(&self, pos: &Position) -> Option<&Reach> {
+        self.reached.get(pos)
+    }
+    pub fn contains(&self, pos: &Position) -> bool {
+        self.reached.contains_key(pos)
+    }
+    pub fn cost(&self, pos: &Position) -> Option<usize> {
+        self.reached.get(pos).map(|reach| reach.cost)
+    }
+    pub fn len(&self) -> usize {
+        self.reached.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.reached.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&Position, &Reach)> {
+        self.reached.iter()
+    }
+    pub fn positions(&self) -> impl Iterator<Item = &Position> {
+        self.reached.keys()
+    }
+    /// Walks the predecessors back from `target`, returning the path from the
+    /// origin to `target` (both included) or `None` if it is out of range.
+    pub fn path_to(&self, target: Position) -> Option<Vec<Position>> {
+        let mut path = Vec::new();
+        let mut current = target;
+        loop {
+            path.push(current);
+            match self.reached.get(&current)?.parent {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+        path.reverse();
+        Some(path)
+    }
+}
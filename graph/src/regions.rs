@@ -0,0 +1,220 @@
+use crate::{is_odd, Cell, Direction, Grid, GridSet, GridType, Position};
+use std::collections::{HashMap, VecDeque};
+use strum::IntoEnumIterator;
+
+/// Labels every open cell with the connected region it belongs to, so whether
+/// two cells can reach each other is a lookup instead of a search. Regions are
+/// 8-connected when `diagonal` is set and 4-connected otherwise, matching the
+/// moves the solvers make.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regions {
+    pub width: usize,
+    pub height: usize,
+    pub diagonal: bool,
+    labels: Vec<Option<usize>>,
+    sizes: HashMap<usize, usize>,
+    next: usize,
+}
+
+impl Regions {
+    pub fn new(grid_type: GridType, diagonal: bool) -> Self {
+        let (width, height) = match grid_type {
+            GridType::Full(grid) => grid.dimension(),
+            GridType::Set(set) => set.dimension(),
+            GridType::Chunked(chunks) => chunks.dimension(),
+        };
+        let open = |pos: Position| match grid_type {
+            GridType::Full(grid) => grid.terrain(pos) != Some(Cell::Block),
+            GridType::Set(set) => !set.set.contains(&pos),
+            GridType::Chunked(chunks) => !chunks.is_blocked_at(pos),
+        };
+        let mut regions = Self {
+            width,
+            height,
+            diagonal,
+            labels: vec![None; width * height],
+            sizes: HashMap::new(),
+            next: 0,
+        };
+        // open cells start out with a placeholder label that the flood fill
+        // below replaces region by region
+        let unlabelled = usize::MAX;
+        let positions: Vec<Position> = regions.positions().collect();
+        for pos in positions.iter().copied().filter(|pos| open(*pos)) {
+            regions.set_label(pos, Some(unlabelled));
+        }
+        for pos in positions {
+            if regions.region(pos) == Some(unlabelled) {
+                regions.fill(pos, unlabelled);
+            }
+        }
+        regions
+    }
+    pub fn dimension(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+    /// The label of the region `pos` is in, or `None` for walls and positions
+    /// outside the grid.
+    pub fn region(&self, pos: Position) -> Option<usize> {
+        self.index(pos).and_then(|i| self.labels[i])
+    }
+    pub fn connected(&self, from: Position, to: Position) -> bool {
+        match (self.region(from), self.region(to)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+    /// Number of cells in the region labelled `label`.
+    pub fn size(&self, label: usize) -> usize {
+        self.sizes.get(&label).copied().unwrap_or(0)
+    }
+    /// Number of regions.
+    pub fn len(&self) -> usize {
+        self.sizes.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.sizes.is_empty()
+    }
+    pub fn labels(&self) -> impl Iterator<Item = usize> + '_ {
+        self.sizes.keys().copied()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Position, usize)> + '_ {
+        self.positions()
+            .filter_map(move |pos| self.region(pos).map(|label| (pos, label)))
+    }
+    /// Every position, in row-major order.
+    fn positions(&self) -> impl Iterator<Item = Position> {
+        let (width, height) = self.dimension();
+        (0..height).flat_map(move |y| (0..width).map(move |x| Position::new(x, y)))
+    }
+    /// Updates the labels after the cell at `pos` became a wall or open
+    /// ground. Opening a cell merges the regions around it; closing one only
+    /// refloods the region it was part of.
+    pub fn set_blocked(&mut self, pos: Position, blocked: bool) {
+        if !self.contains(pos) {
+            return;
+        }
+        match (self.region(pos), blocked) {
+            (Some(label), true) => {
+                self.set_label(pos, None);
+                self.shrink(label, 1);
+                for n in self.neighbours(pos) {
+                    if self.region(n) == Some(label) {
+                        self.fill(n, label);
+                    }
+                }
+            }
+            (None, false) => {
+                let mut around: Vec<usize> = self
+                    .neighbours(pos)
+                    .into_iter()
+                    .filter_map(|n| self.region(n))
+                    .collect();
+                around.sort_unstable();
+                around.dedup();
+                let label = match around.iter().max_by_key(|label| self.size(**label)) {
+                    Some(label) => *label,
+                    None => {
+                        self.next += 1;
+                        self.next - 1
+                    }
+                };
+                self.set_label(pos, Some(label));
+                *self.sizes.entry(label).or_insert(0) += 1;
+                for other in around.into_iter().filter(|other| *other != label) {
+                    if let Some(n) = self
+                        .neighbours(pos)
+                        .into_iter()
+                        .find(|n| self.region(*n) == Some(other))
+                    {
+                        self.fill_into(n, other, label);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    /// Applies every wall that differs between `grid` and the labels. A grid
+    /// of a different size is labelled from scratch.
+    pub fn sync(&mut self, grid: &Grid) {
+        if grid.dimension() != self.dimension() {
+            *self = Self::new(GridType::Full(grid), self.diagonal);
+            return;
+        }
+        for pos in grid.positions() {
+            let blocked = grid.terrain(pos) == Some(Cell::Block);
+            if self.region(pos).is_none() != blocked {
+                self.set_blocked(pos, blocked);
+            }
+        }
+    }
+    /// Moves the cells connected to `from` that carry `old` into a new region.
+    fn fill(&mut self, from: Position, old: usize) {
+        let label = self.next;
+        self.next += 1;
+        self.fill_into(from, old, label);
+    }
+    fn fill_into(&mut self, from: Position, old: usize, new: usize) {
+        let mut queue = VecDeque::new();
+        self.set_label(from, Some(new));
+        queue.push_back(from);
+        let mut count = 0;
+        while let Some(pos) = queue.pop_front() {
+            count += 1;
+            for n in self.neighbours(pos) {
+                if self.region(n) == Some(old) {
+                    self.set_label(n, Some(new));
+                    queue.push_back(n);
+                }
+            }
+        }
+        self.shrink(old, count);
+        *self.sizes.entry(new).or_insert(0) += count;
+    }
+    fn shrink(&mut self, label: usize, by: usize) {
+        if let Some(size) = self.sizes.get_mut(&label) {
+            *size = size.saturating_sub(by);
+            if *size == 0 {
+                self.sizes.remove(&label);
+            }
+        }
+    }
+    fn neighbours(&self, pos: Position) -> Vec<Position> {
+        let (x, y) = (pos.x as isize, pos.y as isize);
+        Direction::iter()
+            .enumerate()
+            .filter(|(i, _)| is_odd(*i) || self.diagonal)
+            .map(|(_, dir)| dir.get_coordinate(x, y))
+            .filter(|(x, y)| *x >= 0 && *y >= 0)
+            .map(|(x, y)| Position::new(x as usize, y as usize))
+            .filter(|pos| self.contains(*pos))
+            .collect()
+    }
+    fn set_label(&mut self, pos: Position, label: Option<usize>) {
+        if let Some(i) = self.index(pos) {
+            self.labels[i] = label;
+        }
+    }
+    fn contains(&self, pos: Position) -> bool {
+        pos.x < self.width && pos.y < self.height
+    }
+    fn index(&self, pos: Position) -> Option<usize> {
+        if self.contains(pos) {
+            Some(pos.y * self.width + pos.x)
+        } else {
+            None
+        }
+    }
+}
+
+impl Grid {
+    pub fn regions(&self, diagonal: bool) -> Regions {
+        Regions::new(GridType::Full(self), diagonal)
+    }
+}
+
+impl GridSet {
+    pub fn regions(&self, diagonal: bool) -> Regions {
+        Regions::new(GridType::Set(self), diagonal)
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{Cell, Grid, GridSet, Position};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Run-length encodes which cells are blocked, in row-major order. The result
+/// is a comma separated list of run lengths that alternate between open and
+/// blocked cells, starting with open ones, so `"0,3,5"` is three walls followed
+/// by five open cells. A trailing open run may be left out.
+pub fn encode_rle(blocked: impl Iterator<Item = bool>) -> String {
+    let mut runs = Vec::new();
+    let (mut current, mut run) = (false, 0);
+    for each in blocked {
+        if each != current {
+            runs.push(run.to_string());
+            current = each;
+            run = 0;
+        }
+        run += 1;
+    }
+    if current {
+        runs.push(run.to_string());
+    }
+    runs.join(",")
+}
+
+/// Decodes `encode_rle` output for a grid of `len` cells.
+pub fn decode_rle(text: &str, len: usize) -> Result<Vec<bool>, String> {
+    let mut blocked = Vec::with_capacity(len);
+    let mut current = false;
+    for run in text.split(',').filter(|run| !run.is_empty()) {
+        let run: usize = run
+            .trim()
+            .parse()
+            .map_err(|_| format!("`{}` is not a valid run length!", run))?;
+        if run > len - blocked.len() {
+            return Err(format!(
+                "The runs cover more than the {} cells of the grid!",
+                len
+            ));
+        }
+        blocked.resize(blocked.len() + run, current);
+        current = !current;
+    }
+    blocked.resize(len, false);
+    Ok(blocked)
+}
+
+/// The blocked cells of a `Request`, either as a list of positions or as the
+/// much smaller run-length encoding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Blocked {
+    List(Vec<Position>),
+    Rle(String),
+}
+
+impl Default for Blocked {
+    fn default() -> Self {
+        Self::List(Vec::new())
+    }
+}
+
+impl From<&Grid> for Blocked {
+    fn from(grid: &Grid) -> Self {
+        Self::Rle(grid.to_rle())
+    }
+}
+
+impl Blocked {
+    /// The blocked positions, or an error if the grid is too large or any of
+    /// them lies outside it.
+    pub fn positions(&self, dimension: (usize, usize)) -> Result<Vec<Position>, String> {
+        let (width, height) = dimension;
+        Grid::cell_count(width, height)?;
+        match self {
+            Blocked::List(list) => {
+                if let Some(pos) = list.iter().find(|pos| pos.x >= width || pos.y >= height) {
+                    return Err(format!(
+                        "The blocked cell {} is outside the {}x{} grid!",
+                        pos, width, height
+                    ));
+                }
+                Ok(list.clone())
+            }
+            Blocked::Rle(text) => {
+                Ok(Grid::from_rle(width, height, text)?.positions_of(Cell::Block))
+            }
+        }
+    }
+}
+
+impl Grid {
+    pub fn to_rle(&self) -> String {
+        encode_rle(
+            self.positions()
+                .map(|pos| self.terrain(pos) == Some(Cell::Block)),
+        )
+    }
+    pub fn from_rle(width: usize, height: usize, text: &str) -> Result<Self, String> {
+        let blocked = decode_rle(text, Grid::cell_count(width, height)?)?;
+        let mut grid = Grid::new(width, height);
+        let positions: Vec<Position> = grid.positions().collect();
+        for (pos, blocked) in positions.into_iter().zip(blocked) {
+            if blocked {
+                grid.set(pos, Cell::Block);
+            }
+        }
+        Ok(grid)
+    }
+}
+
+impl GridSet {
+    pub fn to_rle(&self) -> String {
+        encode_rle(self.positions().map(|pos| self.set.contains(&pos)))
+    }
+    pub fn from_rle(width: usize, height: usize, text: &str) -> Result<Self, String> {
+        let set: HashSet<Position> = Blocked::Rle(text.to_string())
+            .positions((width, height))?
+            .into_iter()
+            .collect();
+        Ok(Self { width, height, set })
+    }
+}
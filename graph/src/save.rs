@@ -0,0 +1,177 @@
+use crate::{AStarConfig, Blocked, Cell, Grid, Position, Request};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+pub const SAVE_VERSION: u32 = 7;
+
+/// Marks the start of the binary format, followed by the version as a little
+/// endian `u32` and the bincode encoded save.
+const MAGIC: &[u8; 4] = b"ASTR";
+
+/// A grid, its end points and the search settings, as written to disk.
+///
+/// Version 1 was the `Request` sent to the backend: a dimension and the list of
+/// blocked cells. Version 2 stores the whole grid, so search state survives.
+/// Every later version appends one field to the config: version 3 the
+/// weighting of the search, version 4 the algorithm, version 5 the corner
+/// cutting policy, version 6 the stencil and version 7 the turn costs.
+///
+/// JSON saves of any version fill in the fields they lack with defaults.
+/// Bincode can't tell where a struct ends, so `from_bytes` reads the config
+/// field by field, up to the fields the save's version has.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SaveFile {
+    pub version: u32,
+    pub grid: Grid,
+    pub config: AStarConfig,
+}
+
+#[derive(Deserialize)]
+struct Version {
+    version: Option<u32>,
+}
+
+impl TryFrom<Request> for SaveFile {
+    type Error = String;
+
+    fn try_from(request: Request) -> Result<Self, Self::Error> {
+        let (width, height) = request.dimension;
+        let mut grid = Grid::new(width, height);
+        for pos in request.walls()? {
+            grid.set(pos, Cell::Block);
+        }
+        let config = request.a_star;
+        Self::check_end_points(&grid, &config)?;
+        grid.set_start(config.start);
+        grid.set_end(config.target);
+        Ok(Self::new(grid, config))
+    }
+}
+
+impl SaveFile {
+    pub fn new(grid: Grid, config: AStarConfig) -> Self {
+        Self {
+            version: SAVE_VERSION,
+            grid,
+            config,
+        }
+    }
+    pub fn end_points(&self) -> (Position, Position) {
+        (self.config.start, self.config.target)
+    }
+    pub fn request(&self) -> Request {
+        Request {
+            dimension: self.grid.dimension(),
+            blocked: Blocked::from(&self.grid),
+            a_star: self.config,
+            seed: None,
+            generator: None,
+            vehicle: None,
+        }
+    }
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend(bincode::serialize(self).unwrap());
+        bytes
+    }
+    /// Reads either format, migrating older versions to the current one.
+    /// Fails if the end points of the search lie outside the grid.
+    pub fn load(bytes: &[u8]) -> Result<Self, String> {
+        let save = if bytes.starts_with(MAGIC) {
+            Self::from_bytes(bytes)?
+        } else {
+            let text = std::str::from_utf8(bytes)
+                .map_err(|_| "The save is neither binary nor valid UTF-8 JSON!".to_string())?;
+            Self::from_json(text)?
+        };
+        Self::check_end_points(&save.grid, &save.config)?;
+        Ok(save)
+    }
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let invalid = |e: serde_json::Error| format!("Invalid save file: {}", e);
+        let version = serde_json::from_str::<Version>(text)
+            .map_err(invalid)?
+            .version
+            .unwrap_or(1);
+        match version {
+            1 => SaveFile::try_from(serde_json::from_str::<Request>(text).map_err(invalid)?),
+            2..=SAVE_VERSION => serde_json::from_str(text)
+                .map(|save: Self| Self::new(save.grid, save.config))
+                .map_err(invalid),
+            _ => Err(Self::unsupported(version)),
+        }
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 8 || !bytes.starts_with(MAGIC) {
+            return Err("Not a binary save file!".into());
+        }
+        let mut version = [0; 4];
+        version.copy_from_slice(&bytes[4..8]);
+        let version = u32::from_le_bytes(version);
+        if !(2..=SAVE_VERSION).contains(&version) {
+            return Err(Self::unsupported(version));
+        }
+        // the options `bincode::serialize` writes with
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes();
+        let mut payload = bincode::Deserializer::from_slice(&bytes[8..], options);
+        let _version: u32 = read(&mut payload)?;
+        let grid: Grid = read(&mut payload)?;
+        let mut config = AStarConfig {
+            start: read(&mut payload)?,
+            target: read(&mut payload)?,
+            diagonal: read(&mut payload)?,
+            multithreaded: read(&mut payload)?,
+            bidirectional: read(&mut payload)?,
+            ..Default::default()
+        };
+        if version >= 3 {
+            config.weighting = read(&mut payload)?;
+        }
+        if version >= 4 {
+            config.algorithm = read(&mut payload)?;
+        }
+        if version >= 5 {
+            config.corner_cutting = read(&mut payload)?;
+        }
+        if version >= 6 {
+            config.stencil = read(&mut payload)?;
+        }
+        if version >= 7 {
+            config.turns = read(&mut payload)?;
+        }
+        Ok(Self::new(grid, config))
+    }
+    fn check_end_points(grid: &Grid, config: &AStarConfig) -> Result<(), String> {
+        for pos in [config.start, config.target] {
+            if !grid.contains(pos) {
+                return Err(format!(
+                    "The end point {} is outside the {}x{} grid!",
+                    pos, grid.width, grid.height
+                ));
+            }
+        }
+        Ok(())
+    }
+    fn unsupported(version: u32) -> String {
+        format!(
+            "Save file version {} isn't supported, the latest is {}!",
+            version, SAVE_VERSION
+        )
+    }
+}
+
+/// The next value of a bincode payload.
+fn read<'de, T, D>(payload: D) -> Result<T, String>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de, Error = bincode::Error>,
+{
+    T::deserialize(payload).map_err(|e| format!("Invalid save file: {}", e))
+}
@@ -0,0 +1,240 @@
+use crate::{Direction, Position};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter, EnumString};
+
+/// One move of a stencil: where it lands relative to the current cell and
+/// what it costs, in the same units as `Direction::g_cost`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Move {
+    pub dx: isize,
+    pub dy: isize,
+    pub cost: usize,
+}
+
+impl Move {
+    pub fn new(dx: isize, dy: isize, cost: usize) -> Self {
+        Self { dx, dy, cost }
+    }
+    /// A move of `(dx, dy)` costing its length, rounded like `Direction::g_cost`.
+    pub fn euclidean(dx: isize, dy: isize) -> Self {
+        let length = Self::new(dx, dy, 0).length();
+        Self::new(dx, dy, (length * 10.).round() as usize)
+    }
+    /// How far the move goes, in cells.
+    pub fn length(&self) -> f64 {
+        ((self.dx * self.dx + self.dy * self.dy) as f64).sqrt()
+    }
+    pub fn is_diagonal_step(&self) -> bool {
+        self.dx.abs() == 1 && self.dy.abs() == 1
+    }
+    /// Where the move lands from `pos`, which may be off the grid.
+    pub fn apply(&self, pos: Position) -> (isize, isize) {
+        (pos.x as isize + self.dx, pos.y as isize + self.dy)
+    }
+}
+
+impl From<Direction> for Move {
+    fn from(direction: Direction) -> Self {
+        let (dx, dy) = direction.offset();
+        Self::new(dx, dy, direction.g_cost())
+    }
+}
+
+/// Up to `Moves::CAPACITY` distinct moves, kept inline so that configs stay
+/// `Copy`. Serialized as a plain list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Move>", into = "Vec<Move>")]
+pub struct Moves {
+    moves: [Move; Moves::CAPACITY],
+    len: usize,
+}
+
+impl Default for Moves {
+    fn default() -> Self {
+        Self {
+            moves: [Move::default(); Moves::CAPACITY],
+            len: 0,
+        }
+    }
+}
+
+impl Moves {
+    pub const CAPACITY: usize = 16;
+
+    pub fn new(moves: &[Move]) -> Result<Self, String> {
+        if moves.len() > Self::CAPACITY {
+            return Err(format!(
+                "A stencil can have at most {} moves but this one has {}!",
+                Self::CAPACITY,
+                moves.len()
+            ));
+        }
+        let mut stencil = Self::default();
+        for each in moves {
+            if (each.dx, each.dy) == (0, 0) || each.cost == 0 {
+                return Err(format!(
+                    "The move {:?} must go somewhere and cost something!",
+                    each
+                ));
+            }
+            if stencil.iter().any(|m| (m.dx, m.dy) == (each.dx, each.dy)) {
+                return Err(format!(
+                    "The stencil moves by ({}, {}) twice!",
+                    each.dx, each.dy
+                ));
+            }
+            stencil.moves[stencil.len] = *each;
+            stencil.len += 1;
+        }
+        Ok(stencil)
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn as_slice(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Move> {
+        self.as_slice().iter()
+    }
+    /// The move that goes from `from` to `to`, if any.
+    pub fn between(&self, from: Position, to: Position) -> Option<&Move> {
+        let landing = (to.x as isize, to.y as isize);
+        self.iter().find(|m| m.apply(from) == landing)
+    }
+    /// The same moves walked backwards, for searching from the target.
+    pub fn reversed(mut self) -> Self {
+        for each in &mut self.moves[..self.len] {
+            each.dx = -each.dx;
+            each.dy = -each.dy;
+        }
+        self
+    }
+    /// Appends moves known to be valid.
+    fn chain(mut self, moves: impl IntoIterator<Item = Move>) -> Self {
+        for each in moves {
+            self.moves[self.len] = each;
+            self.len += 1;
+        }
+        self
+    }
+    /// The lowest cost per unit of distance, which makes straight line
+    /// distance times it a lower bound on the cost of reaching any cell.
+    fn cheapest_rate(&self) -> f64 {
+        self.iter()
+            .map(|m| m.cost as f64 / m.length())
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+impl TryFrom<Vec<Move>> for Moves {
+    type Error = String;
+
+    fn try_from(moves: Vec<Move>) -> Result<Self, Self::Error> {
+        Self::new(&moves)
+    }
+}
+
+impl From<Moves> for Vec<Move> {
+    fn from(moves: Moves) -> Self {
+        moves.as_slice().to_vec()
+    }
+}
+
+/// The moves a search expands from each cell. Moves longer than a step jump
+/// straight to the cell they land on, and only diagonal steps are subject to
+/// the corner cutting policy.
+///
+/// Movement ranges, flow fields, regions and HPA* always use the compass
+/// directions.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    AsRefStr,
+    EnumIter,
+    EnumString,
+    Serialize,
+    Deserialize,
+)]
+// Boxing the custom moves would cost configs their `Copy`.
+#[allow(clippy::large_enum_variant)]
+pub enum Stencil {
+    /// The four straight directions, and the diagonals too when searching
+    /// diagonally.
+    #[default]
+    Compass,
+    /// The eight moves of a chess knight.
+    Knight,
+    /// The compass directions and knight moves.
+    Sixteen,
+    /// The four straight directions and jumps of two cells along them.
+    Jumps,
+    Custom(Moves),
+}
+
+impl Stencil {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stencil::Compass => "Compass",
+            Stencil::Knight => "Knight",
+            Stencil::Sixteen => "16 neighbours",
+            Stencil::Jumps => "Jumps",
+            Stencil::Custom(_) => "Custom",
+        }
+    }
+    pub fn moves(&self, diagonal: bool) -> Moves {
+        const KNIGHT: [(isize, isize); 8] = [
+            (1, -2),
+            (2, -1),
+            (2, 1),
+            (1, 2),
+            (-1, 2),
+            (-2, 1),
+            (-2, -1),
+            (-1, -2),
+        ];
+        let knight = KNIGHT.iter().map(|(dx, dy)| Move::euclidean(*dx, *dy));
+        let none = Moves::default();
+        match self {
+            Stencil::Compass => none.chain(Self::compass(diagonal)),
+            Stencil::Knight => none.chain(knight),
+            Stencil::Sixteen => none.chain(Self::compass(true)).chain(knight),
+            Stencil::Jumps => none
+                .chain(Self::compass(false))
+                .chain(Self::compass(false).map(|m| Move::new(m.dx * 2, m.dy * 2, m.cost * 2))),
+            Stencil::Custom(moves) => *moves,
+        }
+    }
+    /// A lower bound on the cost from `from` to `to`: the octile distance
+    /// for the compass and straight line distance at the cheapest rate of any
+    /// move otherwise.
+    pub fn heuristic(&self, diagonal: bool, from: Position, to: Position) -> usize {
+        match self {
+            Stencil::Compass => from.octile(&to, diagonal),
+            _ => {
+                let (dx, dy) = (from.x as f64 - to.x as f64, from.y as f64 - to.y as f64);
+                let rate = self.moves(diagonal).cheapest_rate();
+                if rate.is_finite() {
+                    ((dx * dx + dy * dy).sqrt() * rate).floor() as usize
+                } else {
+                    0
+                }
+            }
+        }
+    }
+    fn compass(diagonal: bool) -> impl Iterator<Item = Move> {
+        Direction::iter()
+            .filter(move |dir| diagonal || dir.sides().is_none())
+            .map(Move::from)
+    }
+}
@@ -0,0 +1,979 @@
+use a_star_graph::{
+    decode_rle, encode_rle, grid, AStar, AStarBidirectional, AStarConfig, AStarTrait, Algorithm,
+    AnytimeAStar, Blocked, BucketQueue, Cell, ChunkCoord, ChunkedGrid, CornerCutting, Cost,
+    Direction, FlowField, Generator, Grid, GridSet, GridType, HeadingAStar, Hpa, HybridAStar,
+    IdaStar, ImageConfig, Move, Moves, OpenList, Ordered, ParallelAStar, Position, RadixHeap,
+    Regions, Request, Rng, SaveFile, SmaStar, Stencil, TurnCosts, Vehicle, Weighting, CHUNK_SIZE,
+    SAVE_VERSION,
+};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+#[test]
+fn ascii_round_trip() {
+    let text = "S.#\n.ox\n*+E";
+    let grid: Grid = text.parse().unwrap();
+    assert_eq!(grid.dimension(), (3, 3));
+    assert_eq!(grid[Position::new(2, 0)], Cell::Block);
+    assert_eq!(grid.get(Position::new(3, 0)), None);
+    assert_eq!(grid.to_string(), text);
+    assert!("S.\n...".parse::<Grid>().is_err());
+    assert!("S?".parse::<Grid>().is_err());
+}
+
+#[test]
+fn shortest_path_around_wall() {
+    let mut grid = grid![
+        "S.#.."
+        "#.#.#"
+        "#...E"
+    ];
+    let mut a_star = AStar::new(Position::new(0, 0), Position::new(4, 2), false);
+    for each in a_star.solve(GridType::Full(&grid)) {
+        grid.set(each, Cell::ShortestPath);
+    }
+    assert_eq!(
+        grid,
+        grid![
+            "S*#.."
+            "#*#.#"
+            "#***E"
+        ]
+    );
+}
+
+#[test]
+fn memory_bounded_solvers_find_the_same_path() {
+    let grid = grid![
+        "S.#.."
+        "#.#.#"
+        "#...E"
+    ];
+    let config = AStarConfig {
+        start: Position::new(0, 0),
+        target: Position::new(4, 2),
+        diagonal: false,
+        ..Default::default()
+    };
+    let expected = AStar::new(config.start, config.target, false).solve(GridType::Full(&grid));
+    assert_eq!(expected[0], Position::new(3, 2));
+    for limit in [8, 64] {
+        let path = IdaStar::new(config, limit).solve(GridType::Full(&grid));
+        assert_eq!(path, expected);
+        let mut sma = SmaStar::new(config, limit);
+        let path = sma.solve(GridType::Full(&grid));
+        assert_eq!(path, expected);
+        assert!(sma.len() <= limit);
+    }
+    assert!(IdaStar::new(config, 4)
+        .solve(GridType::Full(&grid))
+        .is_empty());
+    assert!(SmaStar::new(config, 4)
+        .solve(GridType::Full(&grid))
+        .is_empty());
+}
+
+#[test]
+fn corner_cutting_policies() {
+    let one_side = grid![
+        "S#"
+        ".E"
+    ];
+    let both_sides = grid![
+        "S#"
+        "#E"
+    ];
+    let (start, target) = (Position::new(0, 0), Position::new(1, 1));
+    let cost = |grid: &Grid, corner_cutting| {
+        grid.reachable(start, usize::MAX, true, corner_cutting)
+            .cost(&target)
+    };
+    assert_eq!(cost(&one_side, CornerCutting::Always), Some(14));
+    assert_eq!(cost(&one_side, CornerCutting::OneSideOpen), Some(14));
+    assert_eq!(cost(&one_side, CornerCutting::Never), Some(20));
+    assert_eq!(cost(&both_sides, CornerCutting::Always), Some(14));
+    assert_eq!(cost(&both_sides, CornerCutting::OneSideOpen), None);
+    assert_eq!(cost(&both_sides, CornerCutting::Never), None);
+}
+
+#[test]
+fn stencils_expand_their_own_moves() {
+    let grid = grid![
+        "S#E"
+        ".#."
+    ];
+    let config = AStarConfig {
+        start: Position::new(0, 0),
+        target: Position::new(2, 0),
+        ..Default::default()
+    };
+    let cost = |stencil| {
+        AnytimeAStar::new(AStarConfig { stencil, ..config })
+            .solve(GridType::Full(&grid))
+            .map(|solution| solution.cost)
+    };
+    assert_eq!(cost(Stencil::Compass), None);
+    assert_eq!(cost(Stencil::Knight), None);
+    assert_eq!(cost(Stencil::Jumps), Some(20));
+    assert_eq!(cost(Stencil::Sixteen), Some(32));
+    let moves = Moves::new(&[Move::new(2, 0, 15)]).unwrap();
+    assert_eq!(cost(Stencil::Custom(moves)), Some(15));
+    assert!(Moves::new(&[Move::new(0, 0, 10)]).is_err());
+}
+
+#[test]
+fn solvers_are_generic_over_costs() {
+    let grid = grid![
+        "S.#.."
+        "#.#.#"
+        "#...E"
+    ];
+    let (start, target) = (Position::new(0, 0), Position::new(4, 2));
+    let sorted = |mut path: Vec<Position>| {
+        path.sort_by_key(|pos| (pos.x, pos.y));
+        path
+    };
+    let expected = sorted(AStar::new(start, target, true).solve(GridType::Full(&grid)));
+    let path = AStar::<u32>::with_cost_type(start, target, true).solve(GridType::Full(&grid));
+    assert_eq!(sorted(path), expected);
+    let path =
+        AStar::<Ordered<f64>>::with_cost_type(start, target, true).solve(GridType::Full(&grid));
+    assert_eq!(sorted(path), expected);
+    let near_max = Cost {
+        g_cost: u32::MAX - 1,
+        h_cost: 5,
+    };
+    assert_eq!(near_max.f_cost(), u32::MAX);
+    assert_eq!(Cost::<Ordered<f32>>::default().f_cost(), Ordered(f32::MAX));
+}
+
+#[test]
+fn turns_cost_extra() {
+    let grid = grid![
+        "S.."
+        "..."
+        "..E"
+    ];
+    let config = AStarConfig {
+        start: Position::new(0, 0),
+        target: Position::new(2, 2),
+        diagonal: false,
+        ..Default::default()
+    };
+    let turns = TurnCosts::default();
+    let solution = HeadingAStar::new(config, turns)
+        .solve(GridType::Full(&grid))
+        .unwrap();
+    assert_eq!((solution.cost, solution.turns), (40 + turns.square, 1));
+    assert_eq!(solution.steps.last().unwrap().pos, config.target);
+    assert_eq!(solution.path().len(), 3);
+    assert_eq!(turns.cost(Direction::North, Direction::South), None);
+    assert_eq!(
+        turns.cost(Direction::North, Direction::SouthEast),
+        Some(turns.sharp)
+    );
+    let huge = TurnCosts {
+        slight: usize::MAX,
+        square: usize::MAX,
+        sharp: usize::MAX,
+        reverse: Some(usize::MAX),
+    };
+    let solution = HeadingAStar::new(config, huge)
+        .solve(GridType::Full(&grid))
+        .unwrap();
+    assert_eq!((solution.cost, solution.turns), (usize::MAX, 1));
+}
+
+#[test]
+fn hybrid_poses_drive_around_the_bend() {
+    let grid = grid![
+        "S.........#"
+        "..........#"
+        "..........#"
+        "#######...#"
+        "#######...#"
+        "#######...#"
+        "#######..E#"
+    ];
+    let config = AStarConfig {
+        start: Position::new(0, 0),
+        target: Position::new(9, 6),
+        ..Default::default()
+    };
+    let vehicle = Vehicle {
+        turning_radius: 2.,
+        ..Default::default()
+    };
+    let mut search = HybridAStar::new(config, vehicle).unwrap();
+    let path = search.solve(GridType::Full(&grid)).unwrap();
+    assert_eq!(path.poses[0], search.start);
+    assert_eq!(path.poses.last().unwrap().cell(), Some(config.target));
+    for pair in path.poses.windows(2) {
+        let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+        assert!((dx * dx + dy * dy).sqrt() <= 0.11);
+        assert_ne!(grid[pair[1].cell().unwrap()], Cell::Block);
+    }
+    let walled = grid![
+        "S.#."
+        "..#E"
+    ];
+    let config = AStarConfig {
+        target: Position::new(3, 1),
+        ..config
+    };
+    assert_eq!(
+        HybridAStar::new(config, vehicle)
+            .unwrap()
+            .solve(GridType::Full(&walled)),
+        None
+    );
+    let stuck = Vehicle {
+        headings: 0,
+        ..vehicle
+    };
+    assert!(HybridAStar::new(config, stuck).is_err());
+    for huge in [
+        Vehicle {
+            step: 1e18,
+            ..vehicle
+        },
+        Vehicle {
+            steering_angles: usize::MAX,
+            ..vehicle
+        },
+        Vehicle {
+            headings: usize::MAX,
+            ..vehicle
+        },
+    ] {
+        assert!(HybridAStar::new(config, huge).is_err());
+    }
+}
+
+#[test]
+fn unreadable_chunks_are_walls() {
+    let dir = std::env::temp_dir().join(format!("a-star-chunks-{}", std::process::id()));
+    let mut chunks = ChunkedGrid::open(&dir).unwrap();
+    chunks.set_blocked(0, 0, true).unwrap();
+    chunks.set_blocked(CHUNK_SIZE as i64, 0, true).unwrap();
+    chunks.save().unwrap();
+    std::fs::write(dir.join("0_0.chunk"), b"not a chunk").unwrap();
+    let mut chunks = ChunkedGrid::open(&dir).unwrap();
+    assert!(chunks.is_blocked(1, 1));
+    assert!(chunks.is_blocked(CHUNK_SIZE as i64, 0));
+    assert!(!chunks.is_blocked(CHUNK_SIZE as i64 + 1, 0));
+    assert_eq!(chunks.errors().len(), 1);
+    assert!(chunks.set_blocked(1, 1, false).is_err());
+    let (start, target) = (
+        Position::new(CHUNK_SIZE, 1),
+        Position::new(CHUNK_SIZE + 5, 5),
+    );
+    let path = AStar::new(start, target, true).solve(GridType::Chunked(&chunks));
+    assert!(!path.is_empty());
+    assert!(path.iter().all(|pos| pos.x >= CHUNK_SIZE));
+    assert!(AStar::new(Position::new(1, 1), target, true)
+        .solve(GridType::Chunked(&chunks))
+        .is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn serialized_grids_must_match_their_dimension() {
+    let grid = grid![
+        "S.#"
+        ".xE"
+    ];
+    let json = serde_json::to_string(&grid).unwrap();
+    assert_eq!(serde_json::from_str::<Grid>(&json).unwrap(), grid);
+    let short = r#"{"width":2,"height":2,"data":["Path","Block","Path"]}"#;
+    assert!(serde_json::from_str::<Grid>(short).is_err());
+    let huge = format!(r#"{{"width":{},"height":2,"data":[]}}"#, usize::MAX);
+    assert!(serde_json::from_str::<Grid>(&huge).is_err());
+}
+
+#[test]
+fn saves_reject_end_points_outside_the_grid() {
+    let v1 = r#"{"dimension":[3,2],"blocked":[{"x":1,"y":0}],"a_star":{"start":{"x":0,"y":0},"target":{"x":2,"y":1},"diagonal":true,"multithreaded":false,"bidirectional":false}}"#;
+    let save = SaveFile::load(v1.as_bytes()).unwrap();
+    assert_eq!(save.grid.end_points(), Some(save.end_points()));
+    assert_eq!(save.grid[Position::new(1, 0)], Cell::Block);
+    for bad in [
+        v1.replace(r#"{"x":2,"y":1}"#, r#"{"x":3,"y":1}"#),
+        v1.replace(r#"[{"x":1,"y":0}]"#, r#"[{"x":1,"y":2}]"#),
+    ] {
+        assert!(SaveFile::load(bad.as_bytes()).is_err());
+    }
+    let mut config = save.config;
+    config.target = Position::new(5, 5);
+    let outside = SaveFile::new(save.grid, config);
+    assert!(SaveFile::load(&outside.to_bytes()).is_err());
+    assert!(SaveFile::load(outside.to_json().as_bytes()).is_err());
+}
+
+#[test]
+fn binary_saves_of_every_version_load() {
+    let grid = grid![
+        "S.#"
+        "..E"
+    ];
+    let config = AStarConfig {
+        start: Position::new(0, 0),
+        target: Position::new(2, 1),
+        diagonal: false,
+        bidirectional: true,
+        weighting: Weighting::Weighted(1.5),
+        algorithm: Algorithm::Greedy,
+        corner_cutting: CornerCutting::Never,
+        stencil: Stencil::Knight,
+        turns: Some(TurnCosts::default()),
+        ..Default::default()
+    };
+    let save = SaveFile::new(grid.clone(), config);
+    assert_eq!(SaveFile::load(&save.to_bytes()).unwrap(), save);
+    let header = |version: u32| {
+        let mut bytes = b"ASTR".to_vec();
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes
+    };
+    let mut v2 = header(2);
+    v2.extend(
+        bincode::serialize(&(2u32, &grid, config.start, config.target, false, false, true))
+            .unwrap(),
+    );
+    let loaded = SaveFile::load(&v2).unwrap();
+    assert_eq!(loaded.grid, grid);
+    assert_eq!(
+        loaded.config,
+        AStarConfig {
+            start: config.start,
+            target: config.target,
+            diagonal: false,
+            bidirectional: true,
+            ..Default::default()
+        }
+    );
+    let mut v5 = header(5);
+    v5.extend(
+        bincode::serialize(&(
+            5u32,
+            &grid,
+            (config.start, config.target, false, false, true),
+            (config.weighting, config.algorithm, config.corner_cutting),
+        ))
+        .unwrap(),
+    );
+    let loaded = SaveFile::load(&v5).unwrap();
+    assert_eq!(
+        loaded.config,
+        AStarConfig {
+            stencil: Stencil::Compass,
+            turns: None,
+            ..config
+        }
+    );
+    assert!(SaveFile::load(&header(2)).is_err());
+    assert!(SaveFile::load(&header(SAVE_VERSION + 1)).is_err());
+}
+
+#[test]
+fn solvers_find_the_shortest_paths() {
+    let length = |a: Position, b: Position| {
+        ((a.x as f64 - b.x as f64).powi(2) + (a.y as f64 - b.y as f64).powi(2)).sqrt() * 10.
+    };
+    let units = |a: Position, b: Position| Direction::between(a, b).unwrap().g_cost() as f64;
+    for seed in 1..6 {
+        let mut grid = Grid::new(24, 16);
+        let (start, target) = grid.generate(Generator::Cave, seed).unwrap();
+        let index = |pos: Position| pos.y * grid.width + pos.x;
+        let shortest = |step_cost: &dyn Fn(Position, Position) -> f64| {
+            let mut best = vec![f64::INFINITY; grid.width * grid.height];
+            let mut done = vec![false; best.len()];
+            best[index(start)] = 0.;
+            while let Some(pos) = grid
+                .positions()
+                .filter(|pos| !done[index(*pos)] && best[index(*pos)].is_finite())
+                .min_by(|a, b| best[index(*a)].total_cmp(&best[index(*b)]))
+            {
+                done[index(pos)] = true;
+                for next in grid.neighbours(pos, true) {
+                    if grid.terrain(next) != Some(Cell::Block) {
+                        let cost = best[index(pos)] + step_cost(pos, next);
+                        if cost < best[index(next)] {
+                            best[index(next)] = cost;
+                        }
+                    }
+                }
+            }
+            best[index(target)]
+        };
+        let path_cost = |path: Vec<Position>, step_cost: &dyn Fn(Position, Position) -> f64| {
+            let mut cells = vec![target];
+            cells.extend(path);
+            cells.push(start);
+            cells
+                .windows(2)
+                .map(|pair| step_cost(pair[0], pair[1]))
+                .sum::<f64>()
+        };
+        let config = AStarConfig {
+            start,
+            target,
+            ..Default::default()
+        };
+        let expected = shortest(&length);
+        let path =
+            AStar::<Ordered<f64>>::with_cost_type(start, target, true).solve(GridType::Full(&grid));
+        assert!(
+            (path_cost(path, &length) - expected).abs() < 1e-6,
+            "seed {}",
+            seed
+        );
+        let path =
+            AStarBidirectional::<Ordered<f64>>::with_cost_type(config).solve(GridType::Full(&grid));
+        assert!(
+            (path_cost(path, &length) - expected).abs() < 1e-6,
+            "seed {}",
+            seed
+        );
+        let expected = shortest(&units);
+        let path = AStar::new(start, target, true).solve(GridType::Full(&grid));
+        assert_eq!(path_cost(path, &units), expected, "seed {}", seed);
+        let path = AStarBidirectional::new(config).solve(GridType::Full(&grid));
+        assert_eq!(path_cost(path, &units), expected, "seed {}", seed);
+        let path = ParallelAStar::new(start, target, true).solve(GridType::Full(&grid));
+        assert_eq!(path_cost(path, &units), expected, "seed {}", seed);
+    }
+}
+
+#[test]
+fn a_star_takes_any_open_list() {
+    for seed in 1..6 {
+        let mut grid = Grid::new(32, 24);
+        let (start, target) = grid.generate(Generator::Cave, seed).unwrap();
+        let cost = |path: Vec<Position>| {
+            let mut cells = vec![target];
+            cells.extend(path);
+            cells.push(start);
+            cells
+                .windows(2)
+                .map(|pair| Direction::between(pair[0], pair[1]).unwrap().g_cost())
+                .sum::<usize>()
+        };
+        let expected = cost(AStar::new(start, target, true).solve(GridType::Full(&grid)));
+        let span = 2 * Direction::NorthEast.g_cost();
+        let mut buckets = AStar::with_open_list(start, target, true, BucketQueue::new(span));
+        assert_eq!(cost(buckets.solve(GridType::Full(&grid))), expected);
+        let mut radix = AStar::with_open_list(start, target, true, RadixHeap::new());
+        assert_eq!(cost(radix.solve(GridType::Full(&grid))), expected);
+        let (_, closed) = radix.get_open_and_closed_list();
+        let mut unique = closed.clone();
+        unique.sort_by_key(|pos| (pos.x, pos.y));
+        unique.dedup();
+        assert_eq!(unique.len(), closed.len());
+        radix.clear();
+        assert_eq!(radix.get_open_and_closed_list(), (vec![start], vec![]));
+        assert_eq!(cost(radix.solve(GridType::Full(&grid))), expected);
+    }
+}
+
+#[test]
+fn bucket_queue_wraps_around_its_ring() {
+    let mut queue = BucketQueue::new(3);
+    let pop = |queue: &mut BucketQueue<char>| {
+        let peeked = queue.peek().map(|(item, key)| (*item, key));
+        let popped = queue.pop();
+        assert_eq!(peeked, popped);
+        popped
+    };
+    queue.push('b', 0);
+    queue.push('a', 2);
+    assert_eq!(pop(&mut queue), Some(('b', 0)));
+    queue.push('c', 3);
+    assert_eq!(pop(&mut queue), Some(('a', 2)));
+    // 4 and 5 wrap around to the buckets 0 and 1 were in
+    queue.push('d', 5);
+    queue.push('e', 4);
+    assert_eq!(queue.len(), 3);
+    assert_eq!(pop(&mut queue), Some(('c', 3)));
+    assert_eq!(pop(&mut queue), Some(('e', 4)));
+    assert_eq!(pop(&mut queue), Some(('d', 5)));
+    assert_eq!(pop(&mut queue), None);
+    let mut queue = BucketQueue::new(3);
+    queue.push('g', 10);
+    assert!(std::panic::catch_unwind(move || queue.push('h', 14)).is_err());
+}
+
+#[test]
+fn radix_heap_redistributes_buckets() {
+    let mut heap = RadixHeap::new();
+    let pop = |heap: &mut RadixHeap<usize>| {
+        let peeked = heap.peek().map(|(item, key)| (*item, key));
+        let popped = heap.pop();
+        assert_eq!(peeked, popped);
+        popped.map(|(_, key)| key)
+    };
+    for key in [5, 1, 9, 1024, 3, 7, 1] {
+        heap.push(key, key);
+    }
+    assert_eq!(pop(&mut heap), Some(1));
+    assert_eq!(pop(&mut heap), Some(1));
+    assert_eq!(pop(&mut heap), Some(3));
+    // pushed after the buckets below 1024 were sorted out around 3
+    heap.push(4, 4);
+    heap.push(1000, 1000);
+    let mut keys = Vec::new();
+    while let Some(key) = pop(&mut heap) {
+        keys.push(key);
+    }
+    assert_eq!(keys, [4, 5, 7, 9, 1000, 1024]);
+    assert!(heap.is_empty());
+    assert!(std::panic::catch_unwind(move || heap.push(0, 0)).is_err());
+}
+
+#[test]
+fn hpa_paths_are_valid_and_near_optimal() {
+    for seed in 1..6 {
+        let mut grid = Grid::new(48, 32);
+        let (start, target) = grid.generate(Generator::Cave, seed).unwrap();
+        let hpa = Hpa::new(&grid, 8, true, CornerCutting::Always);
+        let path = hpa.find_path(start, target).unwrap();
+        assert_eq!((path[0], *path.last().unwrap()), (start, target));
+        let mut cost = 0;
+        for pair in path.windows(2) {
+            let dir = Direction::between(pair[0], pair[1]).unwrap();
+            assert_ne!(grid.terrain(pair[1]), Some(Cell::Block));
+            cost += dir.g_cost();
+        }
+        let optimal = grid
+            .reachable(start, usize::MAX, true, CornerCutting::Always)
+            .cost(&target)
+            .unwrap();
+        assert!(cost >= optimal, "seed {}", seed);
+        // crossing clusters only at their transitions costs up to a third
+        // more than the best path on these maps
+        assert!(
+            cost * 3 <= optimal * 4,
+            "seed {}: {} vs {}",
+            seed,
+            cost,
+            optimal
+        );
+        let mut solved = hpa.solve(start, target);
+        solved.reverse();
+        assert_eq!(solved, path[1..path.len() - 1]);
+    }
+}
+
+#[test]
+fn hpa_sync_matches_a_fresh_abstraction() {
+    let mut grid = Grid::new(40, 24);
+    let (start, target) = grid.generate(Generator::Cave, 3).unwrap();
+    let mut hpa = Hpa::new(&grid, 8, true, CornerCutting::Always);
+    let walls = [
+        Position::new(8, 5),
+        Position::new(15, 16),
+        Position::new(23, 7),
+    ];
+    for pos in walls
+        .iter()
+        .copied()
+        .filter(|pos| *pos != start && *pos != target)
+    {
+        let cell = if grid.terrain(pos) == Some(Cell::Block) {
+            Cell::Path
+        } else {
+            Cell::Block
+        };
+        grid.set(pos, cell);
+    }
+    hpa.sync(&grid);
+    let fresh = Hpa::new(&grid, 8, true, CornerCutting::Always);
+    let sorted = |hpa: &Hpa| {
+        let mut nodes: Vec<Position> = hpa.nodes().copied().collect();
+        nodes.sort_by_key(|pos| (pos.x, pos.y));
+        nodes
+    };
+    assert_eq!(sorted(&hpa), sorted(&fresh));
+    let length = |path: Option<Vec<Position>>| {
+        path.map(|path| {
+            path.windows(2)
+                .map(|pair| Direction::between(pair[0], pair[1]).unwrap().g_cost())
+                .sum::<usize>()
+        })
+    };
+    for (from, to) in [(start, target), (target, start)] {
+        assert_eq!(
+            length(hpa.find_path(from, to)),
+            length(fresh.find_path(from, to))
+        );
+    }
+}
+
+#[test]
+fn rle_round_trips_and_rejects_malformed_runs() {
+    let blocked = [true, true, false, false, false, true, false];
+    let text = encode_rle(blocked.iter().copied());
+    assert_eq!(text, "0,2,3,1");
+    assert_eq!(decode_rle(&text, blocked.len()).unwrap(), blocked);
+    assert_eq!(encode_rle(std::iter::empty()), "");
+    assert_eq!(decode_rle("", 3).unwrap(), [false; 3]);
+    assert!(decode_rle("2,x", 7).is_err());
+    assert!(decode_rle("2,-1", 7).is_err());
+    assert!(decode_rle("5,3", 7).is_err());
+    assert!(decode_rle(&format!("1,{}", usize::MAX), 7).is_err());
+    let grid = grid![
+        "S.#.."
+        "#.#.#"
+        "#...E"
+    ];
+    let text = grid.to_rle();
+    let mut walls = Grid::from_rle(5, 3, &text).unwrap();
+    walls.set_start(Position::new(0, 0));
+    walls.set_end(Position::new(4, 2));
+    assert_eq!(walls, grid);
+    assert!(Grid::from_rle(usize::MAX, 2, "").is_err());
+    let blocked = Blocked::Rle(text).positions(grid.dimension()).unwrap();
+    assert_eq!(blocked, grid.positions_of(Cell::Block));
+    let outside = Blocked::List(vec![Position::new(5, 0)]);
+    assert!(outside.positions(grid.dimension()).is_err());
+}
+
+#[test]
+fn map_files_round_trip_and_reject_malformed_input() {
+    let grid = grid![
+        "..#.."
+        "#.#.#"
+        "#...."
+    ];
+    let text = grid.to_map();
+    assert_eq!(
+        text,
+        "type octile\nheight 3\nwidth 5\nmap\n..@..\n@.@.@\n@....\n"
+    );
+    assert_eq!(Grid::from_map(&text).unwrap(), grid);
+    let swamp = Grid::from_map("type octile\nheight 1\nwidth 4\nmap\nGSTW\n").unwrap();
+    assert_eq!(
+        swamp.positions_of(Cell::Block),
+        vec![Position::new(2, 0), Position::new(3, 0)]
+    );
+    for malformed in &[
+        "type octile\nheight 3\nwidth 5\n",
+        "type octile\nwidth 5\nmap\n",
+        "type octile\nheight x\nwidth 5\nmap\n",
+        "size 3\nheight 3\nwidth 5\nmap\n",
+        "height 2\nwidth 2\nmap\n..\n",
+        "height 2\nwidth 2\nmap\n..\n...\n",
+        "height 2\nwidth 2\nmap\n..\n.?\n",
+        "height 1\nwidth 2\nmap\n..\n..\n",
+        "height 4294967296\nwidth 4294967296\nmap\n",
+    ] {
+        assert!(Grid::from_map(malformed).is_err(), "{:?}", malformed);
+    }
+}
+
+#[test]
+fn movement_ranges_stop_at_their_budget() {
+    let grid = grid![
+        "S.#.."
+        ".##.."
+        "....."
+    ];
+    let origin = Position::new(0, 0);
+    let range = grid.reachable(origin, 60, false, CornerCutting::Always);
+    assert_eq!(range.cost(&origin), Some(0));
+    assert_eq!(range.cost(&Position::new(3, 1)), Some(60));
+    assert_eq!(range.cost(&Position::new(3, 0)), None);
+    assert_eq!(range.path_to(Position::new(3, 0)), None);
+    assert_eq!(range.path_to(Position::new(2, 0)), None);
+    assert_eq!(
+        range.path_to(Position::new(2, 2)),
+        Some(vec![
+            Position::new(0, 0),
+            Position::new(0, 1),
+            Position::new(0, 2),
+            Position::new(1, 2),
+            Position::new(2, 2),
+        ])
+    );
+    assert!(range.iter().all(|(_, reach)| reach.cost <= 60));
+    for &diagonal in &[false, true] {
+        let range = grid.reachable(origin, 100, diagonal, CornerCutting::Never);
+        let set = GridSet::from(&grid).reachable(origin, 100, diagonal, CornerCutting::Never);
+        assert_eq!(range.len(), set.len());
+        for (pos, reach) in range.iter() {
+            assert_eq!(set.cost(pos), Some(reach.cost));
+            let path = range.path_to(*pos).unwrap();
+            assert_eq!(path.first(), Some(&origin));
+            assert_eq!(path.last(), Some(pos));
+            let cost: usize = path
+                .windows(2)
+                .map(|pair| Direction::between(pair[0], pair[1]).unwrap().g_cost())
+                .sum();
+            assert_eq!(cost, reach.cost);
+        }
+    }
+}
+
+#[test]
+fn flow_fields_point_down_the_shortest_paths() {
+    let grid = grid![
+        "..#.."
+        "..##."
+        "....#"
+        "###.."
+    ];
+    let target = Position::new(0, 0);
+    let field = FlowField::new(GridType::Full(&grid), target, false, CornerCutting::Always);
+    assert_eq!(field.distance(target), Some(0));
+    assert_eq!(field.direction(target), None);
+    assert_eq!(field.direction(Position::new(1, 0)), Some(Direction::West));
+    assert_eq!(field.direction(Position::new(0, 1)), Some(Direction::North));
+    assert_eq!(field.distance(Position::new(4, 3)), Some(70));
+    for walled_off in &[
+        Position::new(2, 0),
+        Position::new(4, 0),
+        Position::new(5, 0),
+    ] {
+        assert_eq!(field.distance(*walled_off), None);
+        assert_eq!(field.direction(*walled_off), None);
+        assert_eq!(field.path_from(*walled_off), None);
+    }
+    for seed in 1..4 {
+        let mut grid = Grid::new(24, 16);
+        let (_, target) = grid.generate(Generator::Cave, seed).unwrap();
+        let field = FlowField::new(GridType::Full(&grid), target, true, CornerCutting::Never);
+        let range = grid.reachable(target, usize::MAX, true, CornerCutting::Never);
+        for pos in grid.positions() {
+            assert_eq!(field.distance(pos), range.cost(&pos), "seed {}", seed);
+            if let Some(dir) = field.direction(pos) {
+                let next = grid
+                    .neighbours(pos, true)
+                    .into_iter()
+                    .find(|next| Direction::between(pos, *next) == Some(dir))
+                    .unwrap();
+                assert_eq!(
+                    field.distance(next).map(|d| d + dir.g_cost()),
+                    field.distance(pos),
+                    "seed {}",
+                    seed
+                );
+            }
+        }
+        let start = range.positions().max_by_key(|pos| range.cost(pos)).unwrap();
+        let path = field.path_from(*start).unwrap();
+        assert_eq!(path.last(), Some(&target));
+        assert_eq!(path.len(), range.path_to(*start).unwrap().len());
+    }
+}
+
+#[test]
+fn incremental_regions_match_a_full_relabel() {
+    let same_partition = |incremental: &Regions, fresh: &Regions| {
+        assert_eq!(incremental.len(), fresh.len());
+        let mut labels = HashMap::new();
+        let mut back = HashMap::new();
+        for ((pos, a), (_, b)) in incremental.iter().zip(fresh.iter()) {
+            assert_eq!(
+                incremental.region(pos).is_some(),
+                fresh.region(pos).is_some()
+            );
+            assert_eq!(*labels.entry(a).or_insert(b), b, "{:?}", pos);
+            assert_eq!(*back.entry(b).or_insert(a), a, "{:?}", pos);
+            assert_eq!(incremental.size(a), fresh.size(b));
+        }
+    };
+    for &diagonal in &[false, true] {
+        for seed in 1..4 {
+            let mut grid = Grid::new(24, 16);
+            grid.generate(Generator::Cave, seed).unwrap();
+            let mut regions = grid.regions(diagonal);
+            let mut rng = Rng::new(seed);
+            for step in 0..200 {
+                let pos = Position::new(rng.index(grid.width), rng.index(grid.height));
+                let blocked = grid.terrain(pos) != Some(Cell::Block);
+                grid.set(pos, if blocked { Cell::Block } else { Cell::Path });
+                // The last few changes of every ten are left for `sync`.
+                match step % 10 {
+                    0 => regions.sync(&grid),
+                    1..=6 => regions.set_blocked(pos, blocked),
+                    _ => continue,
+                }
+                same_partition(&regions, &grid.regions(diagonal));
+            }
+        }
+    }
+}
+
+const GENERATORS: [Generator; 5] = [
+    Generator::Backtracker,
+    Generator::Prim,
+    Generator::Kruskal,
+    Generator::Division,
+    Generator::Cave,
+];
+
+#[test]
+fn seeds_reproduce_the_same_grids() {
+    let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+    let stream: Vec<u64> = (0..64).map(|_| a.next_u64()).collect();
+    assert!(stream.iter().all(|n| *n == b.next_u64()));
+    assert_ne!(stream[0], Rng::new(8).next_u64());
+    let place = |seed: u64| {
+        let mut grid = Grid::new(16, 12);
+        let end_points = grid.set_rand_start_n_end(&mut Rng::new(seed));
+        (grid, end_points)
+    };
+    assert_eq!(place(3), place(3));
+    assert_ne!(place(3).1, place(4).1);
+    for generator in GENERATORS.iter() {
+        let generate = |seed: u64| {
+            let mut grid = Grid::new(21, 15);
+            let end_points = grid.generate(*generator, seed).unwrap();
+            (grid, end_points)
+        };
+        let (grid, end_points) = generate(3);
+        assert_eq!((grid.clone(), end_points), generate(3), "{:?}", generator);
+        assert_ne!((grid.clone(), end_points), generate(4), "{:?}", generator);
+        let request = Request {
+            dimension: grid.dimension(),
+            seed: Some(3),
+            generator: Some(*generator),
+            ..Default::default()
+        };
+        assert_eq!(
+            request.walls().unwrap(),
+            grid.positions_of(Cell::Block),
+            "{:?}",
+            generator
+        );
+    }
+}
+
+#[test]
+fn generated_end_points_are_connected() {
+    for generator in GENERATORS.iter() {
+        for &(width, height) in &[(21, 15), (20, 14), (9, 16), (2, 3)] {
+            for seed in 0..8 {
+                let mut grid = Grid::new(width, height);
+                let (start, target) = grid.generate(*generator, seed).unwrap();
+                assert!(
+                    grid.regions(false).connected(start, target),
+                    "{:?} {}x{} seed {}",
+                    generator,
+                    width,
+                    height,
+                    seed
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn generating_into_an_empty_grid_places_nothing() {
+    for generator in GENERATORS.iter() {
+        for &(width, height) in &[(0, 0), (0, 5), (5, 0)] {
+            let mut grid = Grid::new(width, height);
+            assert_eq!(grid.generate(*generator, 1), None, "{:?}", generator);
+            let request = Request {
+                dimension: (width, height),
+                seed: Some(1),
+                generator: Some(*generator),
+                ..Default::default()
+            };
+            assert_eq!(request.walls(), Ok(Vec::new()));
+        }
+        let mut grid = Grid::new(1, 1);
+        let end_points = Some((Position::new(0, 0), Position::new(0, 0)));
+        assert_eq!(grid.generate(*generator, 1), end_points, "{:?}", generator);
+    }
+}
+
+fn encode_png(width: u32, height: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    if data.is_empty() {
+        writer.write_chunk(*b"IDAT", &[]).unwrap();
+    } else {
+        writer.write_image_data(data).unwrap();
+    }
+    drop(writer);
+    bytes
+}
+
+#[test]
+fn png_images_become_grids() {
+    let (dark, light, green, yellow) = ([20, 20, 20], [230, 230, 230], [0, 250, 10], [250, 240, 0]);
+    let pixels = [
+        [green, dark, light, light],
+        [light, dark, dark, light],
+        [light, light, green, yellow],
+    ];
+    let data: Vec<u8> = pixels.iter().flatten().flatten().copied().collect();
+    let png = encode_png(4, 3, png::ColorType::RGB, &data);
+    let grid = Grid::from_png(&png, &ImageConfig::default()).unwrap();
+    let expected = grid![
+        "S#.."
+        ".##."
+        "...E"
+    ];
+    assert_eq!(grid, expected);
+    let config = ImageConfig {
+        threshold: 10,
+        ..Default::default()
+    };
+    let grid = Grid::from_png(&png, &config).unwrap();
+    assert!(grid.positions_of(Cell::Block).is_empty());
+    // one transparent dark pixel and one opaque one
+    let data = [0, 0, 0, 255];
+    let png = encode_png(2, 1, png::ColorType::GrayscaleAlpha, &data);
+    let grid = Grid::from_png(&png, &ImageConfig::default()).unwrap();
+    assert_eq!(grid.terrain(Position::new(0, 0)), Some(Cell::Path));
+    assert_eq!(grid.terrain(Position::new(1, 0)), Some(Cell::Block));
+    let huge = encode_png(5000, 4000, png::ColorType::Grayscale, &[]);
+    let err = Grid::from_png(&huge, &ImageConfig::default()).unwrap_err();
+    assert!(err.contains("cells allowed"), "{}", err);
+    assert!(Grid::from_png(b"not a png", &ImageConfig::default()).is_err());
+}
+
+#[test]
+fn chunks_stay_within_the_cell_cap() {
+    let size = CHUNK_SIZE as i64;
+    assert_eq!(
+        ChunkCoord::of(-1, size),
+        Ok((ChunkCoord::new(-1, 1), CHUNK_SIZE - 1))
+    );
+    let last = i32::MAX as i64 * size;
+    assert_eq!(
+        ChunkCoord::of(last, 0).unwrap().0,
+        ChunkCoord::new(i32::MAX, 0)
+    );
+    assert!(ChunkCoord::of(last + size, 0).is_err());
+    assert!(ChunkCoord::of(0, i64::MIN).is_err());
+    let mut chunks = ChunkedGrid::new();
+    chunks.set_blocked(0, 0, true).unwrap();
+    assert!(chunks.set_blocked(last, 0, true).is_err());
+    assert!(chunks.include(i32::MIN as i64 * size, 0).is_err());
+    assert!(chunks.set_blocked(last + size, 0, true).is_err());
+    assert!(chunks.is_blocked(last + size, 0));
+    assert_eq!(chunks.dimension(), (CHUNK_SIZE, CHUNK_SIZE));
+    assert_eq!(chunks.coords(), vec![ChunkCoord::new(0, 0)]);
+    let dir = std::env::temp_dir().join(format!("a-star-far-chunks-{}", std::process::id()));
+    let mut chunks = ChunkedGrid::open(&dir).unwrap();
+    chunks.set_blocked(0, 0, true).unwrap();
+    chunks.save().unwrap();
+    std::fs::copy(dir.join("0_0.chunk"), dir.join("100000_0.chunk")).unwrap();
+    assert!(ChunkedGrid::open(&dir).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+    let grid = grid![
+        "S.#"
+        ".#E"
+    ];
+    let chunks = ChunkedGrid::try_from(&grid).unwrap();
+    assert_eq!(chunks.dimension(), (CHUNK_SIZE, CHUNK_SIZE));
+    assert!(chunks.is_blocked(2, 0) && chunks.is_blocked(1, 1));
+    assert!(!chunks.is_blocked(0, 0));
+}